@@ -0,0 +1,286 @@
+use crate::{BigUint, Literal, Rng, Solver};
+
+/// Default tolerance, so estimates are within a factor of 1.8 of the true count
+pub const DEFAULT_EPSILON: f64 = 0.8;
+/// Default probability that an estimate is outside the tolerance
+pub const DEFAULT_DELTA: f64 = 0.2;
+
+/// Approximate model counter in the style of ApproxMC (Chakraborty et al. 2016).
+/// Random XOR constraints over a sampling set split solutions into cells, until a cell is
+/// small enough to be counted by enumeration. The cell's count times the number of cells
+/// estimates the count of the whole formula, and the median of many estimates is returned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApproxCounter {
+  /// The estimate is within a factor of 1 + epsilon of the true count...
+  pub epsilon: f64,
+  /// ...with probability at least 1 - delta.
+  pub delta: f64,
+  /// Seed for the random XOR constraints
+  pub seed: u64,
+}
+
+impl ApproxCounter {
+  pub const fn new() -> Self {
+    Self {
+      epsilon: DEFAULT_EPSILON,
+      delta: DEFAULT_DELTA,
+      seed: crate::rng::DEFAULT_SEED,
+    }
+  }
+  /// Returns how many solutions a cell can have before it must be split further.
+  pub fn threshold(&self) -> u64 {
+    let e = self.epsilon;
+    (1.0 + 9.84 * (1.0 + e / (1.0 + e)) * (1.0 + 1.0 / e).powi(2)).ceil() as u64
+  }
+  /// Returns how many estimates are needed for the median to be within tolerance.
+  pub fn iterations(&self) -> u32 { (17.0 * (3.0 / self.delta).log2()).ceil() as u32 }
+  /// Estimates the number of assignments to the sampling set which can be extended into a
+  /// solution. Every other variable should be determined by the sampling set, or the count is
+  /// of the projection onto it.
+  ///
+  /// Sampling variables are frozen, and XOR constraints and blocking clauses are only added to
+  /// copies of the solver, one for each estimate.
+  pub fn count(&self, solver: &mut Solver, sampling_set: &[u32]) -> BigUint {
+    for &var in sampling_set {
+      solver.set_frozen(var, true);
+    }
+    let threshold = self.threshold();
+    // formulas with few solutions are counted exactly
+    let count = cell_count(&mut solver.fork(), &[], sampling_set, threshold);
+    if count < threshold {
+      return BigUint::from(count);
+    }
+    let mut rng = Rng::new(self.seed);
+    let mut estimates = vec![];
+    let mut hashes = vec![];
+    let mut prev = 1;
+    for _ in 0..self.iterations() {
+      // each iteration uses a new sequence of XORs, where each cell is split by the next one
+      hashes.clear();
+      let mut hashed = solver.fork();
+      let mut cell = |hashes: &mut Vec<Literal>, m: usize| {
+        while hashes.len() < m {
+          hashes.push(random_xor(&mut hashed, sampling_set, &mut rng));
+        }
+        cell_count(&mut hashed, &hashes[..m], sampling_set, threshold)
+      };
+      // search for the fewest XORs leaving a small cell, starting from the last iteration's
+      let mut m = prev;
+      let mut count = cell(&mut hashes, m);
+      if count >= threshold {
+        while count >= threshold && m < sampling_set.len() {
+          m += 1;
+          count = cell(&mut hashes, m);
+        }
+      } else {
+        while m > 1 {
+          let larger = cell(&mut hashes, m - 1);
+          if larger >= threshold {
+            break;
+          }
+          m -= 1;
+          count = larger;
+        }
+      }
+      prev = m;
+      let mut estimate = BigUint::from(count);
+      estimate <<= m as u32;
+      estimates.push(estimate);
+    }
+    estimates.sort_unstable();
+    estimates.swap_remove(estimates.len() / 2)
+  }
+}
+
+impl Default for ApproxCounter {
+  fn default() -> Self { Self::new() }
+}
+
+/// Counts the solutions projected onto the sampling set where every hash is active, stopping
+/// once there are threshold of them.
+fn cell_count(
+  solver: &mut Solver,
+  hashes: &[Literal],
+  sampling_set: &[u32],
+  threshold: u64,
+) -> u64 {
+  cell_models(solver, hashes, sampling_set, threshold as usize).len() as u64
+}
+
+/// Returns up to limit solutions with distinct projections onto the sampling set where every
+/// hash is active.
+pub(crate) fn cell_models(
+  solver: &mut Solver,
+  hashes: &[Literal],
+  sampling_set: &[u32],
+  limit: usize,
+) -> Vec<Vec<Option<bool>>> {
+  // blocking clauses are only kept while this is assumed
+  let active = Literal::new(solver.new_var(), false);
+  let mut assumptions = hashes.to_vec();
+  assumptions.push(active);
+  let models = solver
+    .enumerate_models_with_assumptions(&assumptions, Some(sampling_set))
+    .take(limit)
+    .collect();
+  solver.add_clause(&[!active]);
+  models
+}
+
+/// Adds a XOR over a random half of the sampling set with a random parity, returning the
+/// literal which must be assumed for it to hold.
+pub(crate) fn random_xor(solver: &mut Solver, sampling_set: &[u32], rng: &mut Rng) -> Literal {
+  let lits = sampling_set
+    .iter()
+    .filter(|_| rng.next_bool())
+    .map(|&var| Literal::new(var, false))
+    .collect::<Vec<_>>();
+  let parity = rng.next_bool();
+  let active = Literal::new(solver.new_var(), false);
+  add_xor(solver, &lits, parity, Some(active));
+  active
+}
+
+/// Adds clauses requiring the XOR of lits to equal parity, whenever active is true if given.
+/// Each intermediate XOR is defined by a new variable, so the encoding is linear in size.
+/// Every clause contains the negation of active, so all are satisfied once it is false.
+pub fn add_xor(solver: &mut Solver, lits: &[Literal], parity: bool, active: Option<Literal>) {
+  let mut clause = vec![];
+  let mut add = |solver: &mut Solver, lits: &[Literal]| {
+    clause.clear();
+    clause.extend(active.map(|a| !a));
+    clause.extend_from_slice(lits);
+    solver.add_clause(&clause);
+  };
+  let (&first, rest) = match lits.split_first() {
+    None if parity => return add(solver, &[]),
+    None => return,
+    Some(split) => split,
+  };
+  let mut acc = first;
+  for &lit in rest {
+    let next = Literal::new(solver.new_var(), false);
+    // next <-> acc ^ lit
+    add(solver, &[!next, acc, lit]);
+    add(solver, &[!next, !acc, !lit]);
+    add(solver, &[next, !acc, lit]);
+    add(solver, &[next, acc, !lit]);
+    acc = next;
+  }
+  add(solver, &[if parity { acc } else { !acc }]);
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_util::{assignments, models, random_clauses};
+
+  #[test]
+  pub fn test_add_xor() {
+    let mut rng = Rng::new(1);
+    for i in 0..100 {
+      let vars = 1 + i % 6;
+      let lits = (0..vars)
+        .filter_map(|var| match rng.below(3) {
+          0 => None,
+          b => Some(Literal::new(var, b == 1)),
+        })
+        .collect::<Vec<_>>();
+      let parity = rng.next_bool();
+      let mut solver = Solver::new();
+      solver.resize(vars);
+      let active = if i % 2 == 0 {
+        None
+      } else {
+        Some(Literal::new(solver.new_var(), false))
+      };
+      add_xor(&mut solver, &lits, parity, active);
+      for model in assignments(vars) {
+        let true_lits = lits.iter().filter(|lit| lit.assn(&model) == Some(true));
+        let odd = true_lits.count() % 2 == 1;
+        let mut assumptions = (0..vars)
+          .map(|var| Literal::new(var, model[var as usize] != Some(true)))
+          .collect::<Vec<_>>();
+        assumptions.extend(active);
+        assert_eq!(solver.solve_with_assumptions(&assumptions), odd == parity);
+        if let Some(active) = active {
+          *assumptions.last_mut().unwrap() = !active;
+          assert!(solver.solve_with_assumptions(&assumptions));
+        }
+      }
+    }
+  }
+
+  /// Returns how many distinct values the sampling set takes in models of the clauses.
+  fn projected_count(vars: u32, clauses: &[Vec<Literal>], sampling_set: &[u32]) -> u64 {
+    let mut projections = models(vars, clauses)
+      .into_iter()
+      .map(|model| {
+        sampling_set
+          .iter()
+          .map(|&var| model[var as usize])
+          .collect()
+      })
+      .collect::<Vec<Vec<_>>>();
+    projections.sort_unstable();
+    projections.dedup();
+    projections.len() as u64
+  }
+
+  #[test]
+  pub fn test_small_counts_are_exact() {
+    let mut rng = Rng::new(2);
+    let counter = ApproxCounter::new();
+    for i in 0..100 {
+      let vars = 1 + i % 10;
+      let num_clauses = rng.below(5 * vars) as usize;
+      let clauses = random_clauses(&mut rng, vars, num_clauses, 3);
+      let sampling_set = (0..vars).filter(|_| rng.below(4) != 0).collect::<Vec<_>>();
+      let expected = projected_count(vars, &clauses, &sampling_set);
+      if expected >= counter.threshold() {
+        continue;
+      }
+      let mut solver = Solver::new();
+      solver.resize(vars);
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      let count = counter.count(&mut solver, &sampling_set);
+      assert_eq!(count, BigUint::from(expected), "{:?}", clauses);
+    }
+  }
+
+  #[test]
+  pub fn test_estimates_within_tolerance() {
+    let mut rng = Rng::new(3);
+    let mut within = 0;
+    let trials = 20;
+    for i in 0..trials {
+      let vars = 10 + i % 4;
+      let clauses = random_clauses(&mut rng, vars, vars as usize, 3);
+      // half of the formulas are counted on a projection
+      let sampling_set = (0..vars)
+        .filter(|&var| i % 2 == 0 || var > 1)
+        .collect::<Vec<_>>();
+      let expected = projected_count(vars, &clauses, &sampling_set) as f64;
+      let mut solver = Solver::new();
+      solver.resize(vars);
+      for clause in &clauses {
+        solver.add_clause(clause);
+      }
+      let mut counter = ApproxCounter::new();
+      counter.seed = i as u64 + 1;
+      let count = counter.count(&mut solver, &sampling_set).log2().exp2();
+      let factor = 1.0 + counter.epsilon;
+      if expected / factor <= count && count <= expected * factor {
+        within += 1;
+      }
+    }
+    // each estimate is within tolerance with probability at least 1 - delta
+    assert!(
+      within as f64 >= trials as f64 * (1.0 - DEFAULT_DELTA),
+      "{}",
+      within
+    );
+  }
+}
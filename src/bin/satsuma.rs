@@ -1,25 +1,367 @@
-use clap::{App, Arg};
-use satsuma::Solver;
+use clap::{App, AppSettings, Arg, SubCommand};
+use satsuma::{
+  parser::{self, IcnfItem},
+  ApproxCounter, Counter, Heuristic, MaxSat, Options, Phase, Sampler, Solver,
+};
+use std::{
+  fs::File,
+  io::{BufWriter, Write},
+};
 
 fn main() {
   let matches = App::new("satsuma")
     .version("0.1")
     .author("jk")
     .about("DIMACS SAT solver")
+    .setting(AppSettings::SubcommandsNegateReqs)
     .arg(
       Arg::with_name("input")
         .short("i")
         .long("input")
         .value_name("DIMACs")
-        .help("The input DIMACs file")
+        .help(
+          "The input DIMACs file, iCNF file with a \"p inccnf\" header, or WCNF file with --maxsat",
+        )
         .required(true)
         .takes_value(true)
         .multiple(true),
     )
+    .arg(
+      Arg::with_name("heuristic")
+        .long("heuristic")
+        .value_name("HEURISTIC")
+        .help("Decision heuristic to branch with")
+        .possible_values(&["vsids", "chb", "lrb"])
+        .default_value("vsids")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("mode-switching")
+        .long("mode-switching")
+        .help("Alternate between focused and stable search"),
+    )
+    .arg(
+      Arg::with_name("phase")
+        .long("phase")
+        .value_name("PHASE")
+        .help("Which value to assign decision variables")
+        .possible_values(&["false", "true", "random", "saved", "target", "best"])
+        .default_value("saved")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("rephase")
+        .long("rephase")
+        .help("Periodically reset saved phases"),
+    )
+    .arg(
+      Arg::with_name("seed")
+        .long("seed")
+        .value_name("SEED")
+        .help("Seed for random choices, the same seed reproduces the same run and 0 is the default")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("random-freq")
+        .long("random-freq")
+        .value_name("FREQ")
+        .help("Probability of picking a random decision variable")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("random-init")
+        .long("random-init")
+        .help("Start variables with small random scores"),
+    )
+    .arg(
+      Arg::with_name("chrono")
+        .long("chrono")
+        .value_name("LEVELS")
+        .help("Backtrack chronologically when backjumping more than this many levels")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("reuse-trail")
+        .long("reuse-trail")
+        .help("Keep decision levels on restarts which would be picked again"),
+    )
+    .arg(
+      Arg::with_name("shrink")
+        .long("shrink")
+        .help("Shrink learnt clauses to one literal per level where possible"),
+    )
+    .arg(
+      Arg::with_name("minimize-binary")
+        .long("minimize-binary")
+        .help("Minimize learnt clauses with binary clauses"),
+    )
+    .arg(
+      Arg::with_name("elim")
+        .long("elim")
+        .help("Eliminate variables by resolution before search"),
+    )
+    .arg(
+      Arg::with_name("subsume")
+        .long("subsume")
+        .help("Remove subsumed clauses and strengthen clauses by self-subsuming resolution"),
+    )
+    .arg(
+      Arg::with_name("probe")
+        .long("probe")
+        .help("Probe variables for failed literals before search"),
+    )
+    .arg(
+      Arg::with_name("hyper-binary")
+        .long("hyper-binary")
+        .help("Add hyper-binary resolvents while probing"),
+    )
+    .arg(
+      Arg::with_name("substitute")
+        .long("substitute")
+        .help("Replace equivalent literals before search"),
+    )
+    .arg(
+      Arg::with_name("block")
+        .long("block")
+        .help("Remove blocked clauses before search"),
+    )
+    .arg(
+      Arg::with_name("vivify")
+        .long("vivify")
+        .help("Periodically shorten clauses by propagating their negation"),
+    )
+    .arg(
+      Arg::with_name("all-solutions")
+        .long("all-solutions")
+        .help(
+          "Print every solution instead of only whether there is one, or one for each assignment to the sampling set given by \"c ind\" lines",
+        ),
+    )
+    .arg(
+      Arg::with_name("max-solutions")
+        .long("max-solutions")
+        .value_name("N")
+        .help("Print up to N solutions like --all-solutions")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("count")
+        .long("count")
+        .help("Count the solutions exactly")
+        .conflicts_with("approx-count"),
+    )
+    .arg(
+      Arg::with_name("approx-count")
+        .long("approx-count")
+        .help("Estimate the number of solutions over the sampling set given by \"c ind\" lines"),
+    )
+    .arg(
+      Arg::with_name("epsilon")
+        .long("epsilon")
+        .value_name("EPSILON")
+        .help("Tolerance of estimated counts, which are within a factor of 1 + EPSILON")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("delta")
+        .long("delta")
+        .value_name("DELTA")
+        .help("Probability that an estimated count is outside the tolerance")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("maxsat")
+        .long("maxsat")
+        .help("Read inputs as weighted CNF and find solutions of least cost"),
+    )
+    .arg(
+      Arg::with_name("maxsat-algorithm")
+        .long("maxsat-algorithm")
+        .value_name("ALGORITHM")
+        .help("Search for solutions of least cost from below with cores, or from above with bounds")
+        .possible_values(&["oll", "lsu"])
+        .default_value("oll")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("preprocess-only")
+        .long("preprocess-only")
+        .help(
+          "Write the simplified input to the output file, with every preprocessing technique if none are given",
+        )
+        .requires("output"),
+    )
+    .arg(
+      Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .value_name("DIMACs")
+        .help("Where to write the simplified input")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("map")
+        .long("map")
+        .value_name("FILE")
+        .help("Where to write clauses needed to extend a model, defaults to the output with .map")
+        .takes_value(true),
+    )
+    .subcommand(
+      SubCommand::with_name("sample")
+        .about("Print nearly uniformly random solutions")
+        .arg(
+          Arg::with_name("samples")
+            .short("n")
+            .value_name("N")
+            .help("How many solutions to print")
+            .default_value("1")
+            .takes_value(true),
+        )
+        .arg(
+          Arg::with_name("seed")
+            .long("seed")
+            .value_name("SEED")
+            .help("Seed for random choices, the same seed reproduces the same samples and 0 is the default")
+            .takes_value(true),
+        )
+        .arg(
+          Arg::with_name("input")
+            .value_name("DIMACs")
+            .help("The input DIMACs file, sampled over the variables of its \"c ind\" lines")
+            .required(true),
+        ),
+    )
     .get_matches();
-  let mut solver = Solver::new();
+  let mut options = Options::new();
+  options.heuristic = match matches.value_of("heuristic").unwrap() {
+    "chb" => Heuristic::Chb,
+    "lrb" => Heuristic::Lrb,
+    _ => Heuristic::Vsids,
+  };
+  options.mode_switching = matches.is_present("mode-switching");
+  options.phase = match matches.value_of("phase").unwrap() {
+    "false" => Phase::False,
+    "true" => Phase::True,
+    "random" => Phase::Random,
+    "target" => Phase::Target,
+    "best" => Phase::Best,
+    _ => Phase::Saved,
+  };
+  options.rephase = matches.is_present("rephase");
+  if let Some(seed) = matches.value_of("seed") {
+    options.seed = seed.parse().expect("Seed must be an unsigned integer");
+  }
+  if let Some(freq) = matches.value_of("random-freq") {
+    options.random_decision_freq = freq.parse().expect("Random frequency must be a number");
+  }
+  options.random_init_activity = matches.is_present("random-init");
+  if let Some(threshold) = matches.value_of("chrono") {
+    options.chrono_threshold = Some(
+      threshold
+        .parse()
+        .expect("Chrono threshold must be a number"),
+    );
+  }
+  options.reuse_trail = matches.is_present("reuse-trail");
+  options.shrink = matches.is_present("shrink");
+  options.minimize_binary = matches.is_present("minimize-binary");
+  options.elim = matches.is_present("elim");
+  options.subsume = matches.is_present("subsume");
+  options.probe = matches.is_present("probe");
+  options.hyper_binary = matches.is_present("hyper-binary");
+  options.substitute = matches.is_present("substitute");
+  options.block = matches.is_present("block");
+  options.vivify = matches.is_present("vivify");
+  let max_solutions = if let Some(max) = matches.value_of("max-solutions") {
+    Some(
+      max
+        .parse()
+        .expect("Max solutions must be an unsigned integer"),
+    )
+  } else if matches.is_present("all-solutions") {
+    Some(usize::MAX)
+  } else {
+    None
+  };
+  let mut approx = if matches.is_present("approx-count") {
+    let mut approx = ApproxCounter::new();
+    approx.seed = options.seed;
+    Some(approx)
+  } else {
+    None
+  };
+  if let Some(approx) = approx.as_mut() {
+    if let Some(epsilon) = matches.value_of("epsilon") {
+      approx.epsilon = epsilon.parse().expect("Epsilon must be a number");
+    }
+    if let Some(delta) = matches.value_of("delta") {
+      approx.delta = delta.parse().expect("Delta must be a number");
+    }
+  }
+  if let Some(matches) = matches.subcommand_matches("sample") {
+    sample(options, matches);
+    return;
+  }
+  if matches.is_present("count") {
+    for file_name in matches.values_of("input").unwrap() {
+      let mut counter = Counter::new();
+      counter
+        .load_dimacs(file_name)
+        .expect("Failed to load DIMACs file");
+      println!("{} {} SOLUTIONS", file_name, counter.count());
+    }
+    return;
+  }
+  if matches.is_present("maxsat") {
+    let lsu = matches.value_of("maxsat-algorithm").unwrap() == "lsu";
+    for file_name in matches.values_of("input").unwrap() {
+      solve_maxsat(options.clone(), file_name, lsu);
+    }
+    return;
+  }
+  if matches.is_present("preprocess-only") && !options.preprocessing() {
+    // only simplifying the input is asked for, so every available technique is used
+    options.elim = true;
+    options.subsume = true;
+    options.probe = true;
+    options.substitute = true;
+    options.block = true;
+  }
+  let mut solver = Solver::with_options(options);
+  if matches.is_present("preprocess-only") {
+    let mut inputs = matches.values_of("input").unwrap();
+    let file_name = inputs.next().unwrap();
+    assert!(
+      inputs.next().is_none(),
+      "Only one input can be preprocessed at a time"
+    );
+    let output = matches.value_of("output").unwrap();
+    let map = matches
+      .value_of("map")
+      .map_or_else(|| format!("{}.map", output), String::from);
+    let mut cnf = BufWriter::new(File::create(output).expect("Failed to create output file"));
+    let mut map = BufWriter::new(File::create(map).expect("Failed to create map file"));
+    let no_conflict = solver
+      .load_dimacs(file_name)
+      .expect("Failed to load DIMACs file");
+    if !no_conflict {
+      writeln!(cnf, "p cnf {} 1\n0", solver.num_vars()).expect("Failed to write DIMACs");
+    }
+    let sat = no_conflict
+      && solver
+        .write_simplified(&mut cnf, &mut map)
+        .expect("Failed to write simplified DIMACs");
+    if !sat {
+      println!("{} UNSAT", file_name);
+    }
+    return;
+  }
   for file_name in matches.values_of("input").unwrap() {
     solver.clear();
+    if parser::is_icnf(file_name).expect("Failed to read input file") {
+      solve_icnf(&mut solver, file_name);
+      continue;
+    }
     let no_conflict = solver
       .load_dimacs(file_name)
       .expect("Failed to load DIMACs file");
@@ -27,6 +369,33 @@ fn main() {
       println!("{:?} UNSAT", file_name);
       continue;
     }
+    if let Some(approx) = approx.as_ref() {
+      let sampling_set = parser::sampling_set(file_name)
+        .expect("Failed to read sampling set")
+        .unwrap_or_else(|| (0..solver.num_vars()).collect());
+      let count = approx.count(&mut solver, &sampling_set);
+      println!(
+        "{} {} SOLUTIONS within a factor of {} with probability {}",
+        file_name,
+        count,
+        1.0 + approx.epsilon,
+        1.0 - approx.delta
+      );
+      continue;
+    }
+    if let Some(max_solutions) = max_solutions {
+      let sampling_set = parser::sampling_set(file_name).expect("Failed to read sampling set");
+      let mut count = 0;
+      for model in solver
+        .enumerate_models(sampling_set.as_deref())
+        .take(max_solutions)
+      {
+        count += 1;
+        print_model(&model);
+      }
+      println!("{} {} SOLUTIONS", file_name, count);
+      continue;
+    }
     let has_solution = solver.solve();
     if has_solution {
       println!("{} SAT", file_name);
@@ -35,3 +404,116 @@ fn main() {
     }
   }
 }
+
+/// Runs each query of an iCNF file against the same solver, printing one result per query.
+fn solve_icnf(solver: &mut Solver, file_name: &str) {
+  let (max_var, items) = parser::from_icnf(file_name).expect("Failed to load iCNF file");
+  solver.resize(max_var);
+  // variables used after the first query must survive preprocessing
+  let first_query = items
+    .iter()
+    .position(|item| matches!(item, IcnfItem::Assumptions(_)))
+    .unwrap_or(items.len());
+  for item in &items[first_query..] {
+    let lits = match item {
+      IcnfItem::Clause(lits) => lits,
+      IcnfItem::Assumptions(lits) => lits,
+    };
+    for lit in lits {
+      solver.set_frozen(lit.var(), true);
+    }
+  }
+  let mut query = 0;
+  for item in &items {
+    match item {
+      IcnfItem::Clause(lits) => {
+        solver.add_clause(lits);
+      },
+      IcnfItem::Assumptions(lits) => {
+        query += 1;
+        if solver.solve_with_assumptions(lits) {
+          println!("{} {} SAT", file_name, query);
+        } else {
+          println!("{} {} UNSAT", file_name, query);
+        }
+      },
+    }
+  }
+}
+
+/// Solves a WCNF file, printing the cost of each better solution as it is found, and then the
+/// best solution, in the format of the MaxSAT Evaluations.
+fn solve_maxsat(options: Options, file_name: &str, lsu: bool) {
+  println!("c {}", file_name);
+  let mut maxsat = MaxSat::with_options(options);
+  let no_conflict = maxsat
+    .load_wcnf(file_name)
+    .expect("Failed to load WCNF file");
+  let improved = |cost, _: &[Option<bool>]| println!("o {}", cost);
+  let sat = no_conflict
+    && if lsu {
+      maxsat.solve_lsu(improved)
+    } else {
+      maxsat.solve_oll(improved)
+    };
+  if !sat {
+    println!("s UNSATISFIABLE");
+    return;
+  }
+  let (_, model) = maxsat.best().unwrap();
+  println!("s OPTIMUM FOUND");
+  let values = model
+    .iter()
+    .map(|&val| if val == Some(true) { '1' } else { '0' })
+    .collect::<String>();
+  println!("v {}", values);
+}
+
+/// Prints the assigned variables of a model as a DIMACs value line.
+fn print_model(model: &[Option<bool>]) {
+  print!("v");
+  for (var, val) in model.iter().enumerate() {
+    match val {
+      Some(true) => print!(" {}", var + 1),
+      Some(false) => print!(" -{}", var + 1),
+      None => (),
+    }
+  }
+  println!(" 0");
+}
+
+/// Prints random solutions of a DIMACs file.
+fn sample(options: Options, matches: &clap::ArgMatches<'_>) {
+  let file_name = matches.value_of("input").unwrap();
+  let samples: usize = matches
+    .value_of("samples")
+    .unwrap()
+    .parse()
+    .expect("Number of samples must be an unsigned integer");
+  let mut sampler = Sampler::new();
+  sampler.seed = options.seed;
+  if let Some(seed) = matches.value_of("seed") {
+    sampler.seed = seed.parse().expect("Seed must be an unsigned integer");
+  }
+  let mut solver = Solver::with_options(options);
+  let no_conflict = solver
+    .load_dimacs(file_name)
+    .expect("Failed to load DIMACs file");
+  if !no_conflict {
+    println!("{} UNSAT", file_name);
+    return;
+  }
+  let sampling_set = parser::sampling_set(file_name)
+    .expect("Failed to read sampling set")
+    .unwrap_or_else(|| (0..solver.num_vars()).collect());
+  let mut count = 0;
+  for model in sampler.samples(&mut solver, &sampling_set).take(samples) {
+    count += 1;
+    print_model(&model);
+  }
+  if count == 0 {
+    println!("{} UNSAT", file_name);
+  } else if count < samples {
+    eprintln!("Only {} of {} samples were found", count, samples);
+  }
+}
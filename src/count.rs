@@ -0,0 +1,786 @@
+use crate::{parser::from_dimacs, CRef, Database, Literal, WatchList};
+use hashbrown::HashMap;
+use rustc_hash::FxHasher;
+use std::{
+  cmp::Ordering,
+  fmt,
+  hash::BuildHasherDefault,
+  io,
+  ops::{AddAssign, Mul, ShlAssign},
+  path::Path,
+  rc::Rc,
+};
+
+/// Components are not cached once the cache holds this many of them
+pub const CACHE_LIMIT: usize = 1 << 22;
+/// Once there are more learnt clauses than this, the older half is dropped
+pub const LEARNT_LIMIT: usize = 1 << 16;
+
+/// Separates the variables of a component from its clauses in a cache key
+const SEPARATOR: u32 = u32::MAX;
+/// Level of unassigned variables
+const INVALID_LEVEL: u32 = u32::MAX;
+
+/// An arbitrary precision unsigned integer, for counts which do not fit in 64 bits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigUint {
+  /// Base 2^32 digits from least to most significant, without leading zeros.
+  limbs: Vec<u32>,
+}
+
+impl BigUint {
+  pub const fn zero() -> Self { Self { limbs: vec![] } }
+  pub fn one() -> Self { Self::from(1) }
+  pub fn is_zero(&self) -> bool { self.limbs.is_empty() }
+  /// Returns the number of bits needed to represent this number.
+  pub fn bits(&self) -> u32 {
+    match self.limbs.last() {
+      None => 0,
+      Some(last) => 32 * self.limbs.len() as u32 - last.leading_zeros(),
+    }
+  }
+  /// Returns the base 2 logarithm of this number, or negative infinity for zero.
+  pub fn log2(&self) -> f64 {
+    let bits = self.bits();
+    if bits <= 64 {
+      let low = self
+        .limbs
+        .iter()
+        .rev()
+        .fold(0u64, |acc, &l| (acc << 32) | l as u64);
+      return (low as f64).log2();
+    }
+    // the top limbs are precise enough
+    let dropped = (bits - 65) / 32 + 1;
+    let mut top = self.clone();
+    top.limbs.drain(..dropped as usize);
+    top.log2() + 32.0 * dropped as f64
+  }
+  fn normalize(&mut self) {
+    while self.limbs.last() == Some(&0) {
+      self.limbs.pop();
+    }
+  }
+}
+
+impl From<u64> for BigUint {
+  fn from(v: u64) -> Self {
+    let mut out = Self {
+      limbs: vec![v as u32, (v >> 32) as u32],
+    };
+    out.normalize();
+    out
+  }
+}
+
+impl AddAssign<&BigUint> for BigUint {
+  fn add_assign(&mut self, o: &BigUint) {
+    if self.limbs.len() < o.limbs.len() {
+      self.limbs.resize(o.limbs.len(), 0);
+    }
+    let mut carry = 0;
+    for (i, limb) in self.limbs.iter_mut().enumerate() {
+      let sum = *limb as u64 + o.limbs.get(i).copied().unwrap_or(0) as u64 + carry;
+      *limb = sum as u32;
+      carry = sum >> 32;
+      if carry == 0 && i >= o.limbs.len() {
+        break;
+      }
+    }
+    if carry > 0 {
+      self.limbs.push(carry as u32);
+    }
+  }
+}
+
+impl Mul for &BigUint {
+  type Output = BigUint;
+  fn mul(self, o: &BigUint) -> BigUint {
+    if self.is_zero() || o.is_zero() {
+      return BigUint::zero();
+    }
+    let mut limbs = vec![0u32; self.limbs.len() + o.limbs.len()];
+    for (i, &a) in self.limbs.iter().enumerate() {
+      let mut carry = 0;
+      for (j, &b) in o.limbs.iter().enumerate() {
+        let prod = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+        limbs[i + j] = prod as u32;
+        carry = prod >> 32;
+      }
+      limbs[i + o.limbs.len()] = carry as u32;
+    }
+    let mut out = BigUint { limbs };
+    out.normalize();
+    out
+  }
+}
+
+/// Multiplies by a power of two.
+impl ShlAssign<u32> for BigUint {
+  fn shl_assign(&mut self, bits: u32) {
+    if self.is_zero() {
+      return;
+    }
+    let (words, bits) = ((bits / 32) as usize, bits % 32);
+    if bits > 0 {
+      let mut carry = 0;
+      for limb in self.limbs.iter_mut() {
+        let next = *limb >> (32 - bits);
+        *limb = (*limb << bits) | carry;
+        carry = next;
+      }
+      if carry > 0 {
+        self.limbs.push(carry);
+      }
+    }
+    self.limbs.splice(0..0, vec![0; words]);
+  }
+}
+
+impl Ord for BigUint {
+  fn cmp(&self, o: &Self) -> Ordering {
+    self
+      .limbs
+      .len()
+      .cmp(&o.limbs.len())
+      .then_with(|| self.limbs.iter().rev().cmp(o.limbs.iter().rev()))
+  }
+}
+
+impl PartialOrd for BigUint {
+  fn partial_cmp(&self, o: &Self) -> Option<Ordering> { Some(self.cmp(o)) }
+}
+
+impl fmt::Display for BigUint {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    const BASE: u64 = 1_000_000_000;
+    // repeatedly divide by BASE, collecting decimal digits in chunks of 9
+    let mut limbs = self.limbs.clone();
+    let mut chunks = vec![];
+    while !limbs.is_empty() {
+      let mut rem = 0;
+      for limb in limbs.iter_mut().rev() {
+        let cur = (rem << 32) | *limb as u64;
+        *limb = (cur / BASE) as u32;
+        rem = cur % BASE;
+      }
+      chunks.push(rem as u32);
+      while limbs.last() == Some(&0) {
+        limbs.pop();
+      }
+    }
+    match chunks.pop() {
+      None => write!(f, "0"),
+      Some(first) => {
+        write!(f, "{}", first)?;
+        for chunk in chunks.iter().rev() {
+          write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+      },
+    }
+  }
+}
+
+/// Exact model counter, which splits the clauses left after each decision into independent
+/// components whose counts are multiplied, and caches the count of each component.
+/// Conflicts are learnt from as in the solver, but learnt clauses never join components.
+#[derive(Debug)]
+pub struct Counter {
+  database: Database,
+  watch_list: WatchList,
+  /// Clauses of the input, which are the only ones that decide components
+  clauses: Vec<CRef>,
+  /// Indices of the input clauses each variable occurs in
+  occs: Vec<Vec<u32>>,
+  /// Learnt clauses, from oldest to newest
+  learnts: Vec<CRef>,
+  /// Learnt units, which are assigned once the counter is back at level 0
+  units: Vec<Literal>,
+
+  assignments: Vec<Option<bool>>,
+  trail: Vec<Literal>,
+  /// Where each decision level starts in the trail
+  level_indeces: Vec<u32>,
+  levels: Vec<u32>,
+  causes: Vec<Option<CRef>>,
+  /// Was a conflict found without any decisions
+  unsat: bool,
+
+  /// Counts of components, keyed by their sorted variables and clauses
+  cache: HashMap<Rc<[u32]>, BigUint, BuildHasherDefault<FxHasher>>,
+  /// Keys in the order they were cached, so that caching can be undone
+  cache_log: Vec<Rc<[u32]>>,
+
+  /// Marks for variables and clauses already in a component, equal to stamp
+  var_stamps: Vec<u32>,
+  clause_stamps: Vec<u32>,
+  stamp: u32,
+
+  /// Number of decisions made while counting
+  pub decisions: u64,
+  /// Number of conflicts found while counting
+  pub conflicts: u64,
+  /// Number of components whose count was found in the cache
+  pub cache_hits: u64,
+  /// Once there are more learnt clauses than this, the older half is dropped
+  pub learnt_limit: usize,
+
+  unit_buf: Vec<(CRef, Literal)>,
+  seen: Vec<bool>,
+  scores: Vec<u32>,
+}
+
+impl Counter {
+  pub fn new() -> Self {
+    Self {
+      database: Database::new(),
+      watch_list: WatchList::new(),
+      clauses: vec![],
+      occs: vec![],
+      learnts: vec![],
+      units: vec![],
+      assignments: vec![],
+      trail: vec![],
+      level_indeces: vec![],
+      levels: vec![],
+      causes: vec![],
+      unsat: false,
+      cache: HashMap::with_hasher(Default::default()),
+      cache_log: vec![],
+      var_stamps: vec![],
+      clause_stamps: vec![],
+      stamp: 0,
+      decisions: 0,
+      conflicts: 0,
+      cache_hits: 0,
+      learnt_limit: LEARNT_LIMIT,
+      unit_buf: vec![],
+      seen: vec![],
+      scores: vec![],
+    }
+  }
+  /// Loads the clauses of a DIMACs file into this counter.
+  pub fn load_dimacs<S: AsRef<Path>>(&mut self, s: S) -> io::Result<()> {
+    let mut db = Database::new();
+    let mut crefs = vec![];
+    from_dimacs(s, &mut db, &mut crefs)?;
+    self.resize(db.max_var);
+    for cref in crefs {
+      self.add_clause(cref.as_slice(&db));
+    }
+    Ok(())
+  }
+  /// Adds a clause which every counted model must satisfy.
+  pub fn add_clause(&mut self, lits: &[Literal]) {
+    debug_assert!(self.level_indeces.is_empty());
+    if self.unsat {
+      return;
+    }
+    let max_var = lits.iter().map(|lit| lit.var() + 1).max().unwrap_or(0);
+    if max_var > self.num_vars() {
+      self.resize(max_var);
+    }
+    let mut clause = lits.to_vec();
+    clause.sort_unstable();
+    clause.dedup();
+    let assignments = &self.assignments;
+    if clause.windows(2).any(|w| w[0].is_negation(w[1]))
+      || clause.iter().any(|lit| lit.assn(assignments) == Some(true))
+    {
+      return;
+    }
+    clause.retain(|lit| lit.assn(assignments).is_none());
+    match clause.len() {
+      0 => self.unsat = true,
+      1 => {
+        let cref = self.database.add_clause_from_slice(&clause);
+        self.assign(clause[0], Some(cref));
+        self.unsat = self.propagate().is_some();
+      },
+      _ => {
+        let cref = self.database.add_clause_from_slice(&clause);
+        let unit = self.watch_list.watch(cref, &self.database);
+        debug_assert!(unit.is_none());
+        for lit in clause.iter() {
+          self.occs[lit.var() as usize].push(self.clauses.len() as u32);
+        }
+        self.clauses.push(cref);
+        self.clause_stamps.push(0);
+      },
+    }
+  }
+  /// Returns the number of variables in this counter
+  pub fn num_vars(&self) -> u32 { self.assignments.len() as u32 }
+  /// Resizes this counter to count models over max_vars variables.
+  pub fn resize(&mut self, max_vars: u32) {
+    let vars = max_vars as usize;
+    self.database.max_var = self.database.max_var.max(max_vars);
+    self.watch_list.resize(max_vars);
+    self.occs.resize_with(vars, Vec::new);
+    self.assignments.resize(vars, None);
+    self.levels.resize(vars, INVALID_LEVEL);
+    self.causes.resize(vars, None);
+    self.var_stamps.resize(vars, 0);
+    self.seen.resize(vars, false);
+    self.scores.resize(vars, 0);
+  }
+  /// Returns the number of assignments to every variable which satisfy every clause.
+  pub fn count(&mut self) -> BigUint {
+    if self.unsat {
+      return BigUint::zero();
+    }
+    let vars = (0..self.num_vars()).collect::<Vec<_>>();
+    self.count_split(&vars)
+  }
+
+  /// Counts models of the clauses over the unassigned variables of vars, by multiplying the
+  /// counts of each independent component.
+  fn count_split(&mut self, vars: &[u32]) -> BigUint {
+    let (components, free) = self.components(vars);
+    let mut count = BigUint::one();
+    count <<= free;
+    let cached = self.cache_log.len();
+    for component in components {
+      let n = self.count_component(component);
+      if n.is_zero() {
+        // Learnt clauses may propagate into other components, so when one component has no
+        // models, counts cached for the others may be too low.
+        for key in self.cache_log.drain(cached..) {
+          self.cache.remove(&key);
+        }
+        return n;
+      }
+      count = &count * &n;
+    }
+    count
+  }
+  /// Counts the models of a component by deciding both values of its most occurring variable.
+  fn count_component(&mut self, key: Rc<[u32]>) -> BigUint {
+    if let Some(count) = self.cache.get(&key) {
+      self.cache_hits += 1;
+      return count.clone();
+    }
+    let sep = key.iter().position(|&v| v == SEPARATOR).unwrap();
+    let (vars, clauses) = (&key[..sep], &key[sep + 1..]);
+    let var = self.pick_var(clauses);
+    let mut count = BigUint::zero();
+    for &negated in &[false, true] {
+      let lit = Literal::new(var, negated);
+      // learnt units may have assigned var at level 0 after its other value was counted
+      match lit.assn(&self.assignments) {
+        Some(true) => {
+          count += &self.count_split(vars);
+          continue;
+        },
+        Some(false) => continue,
+        None => (),
+      }
+      self.decisions += 1;
+      self.level_indeces.push(self.trail.len() as u32);
+      self.assign(lit, None);
+      match self.propagate() {
+        None => {
+          count += &self.count_split(vars);
+          self.backtrack();
+        },
+        Some(conflict) => {
+          self.conflicts += 1;
+          let learnt = self.analyze(&conflict);
+          self.backtrack();
+          self.learn(learnt);
+        },
+      }
+      if self.level_indeces.is_empty() && !self.assign_units() {
+        return BigUint::zero();
+      }
+    }
+    if self.cache.len() < CACHE_LIMIT {
+      self.cache.insert(key.clone(), count.clone());
+      self.cache_log.push(key);
+    }
+    count
+  }
+  /// Splits the unassigned variables of vars into components connected by unsatisfied input
+  /// clauses, returned as cache keys, and the number of variables in no such clause.
+  fn components(&mut self, vars: &[u32]) -> (Vec<Rc<[u32]>>, u32) {
+    if self.stamp == u32::MAX {
+      self.var_stamps.iter_mut().for_each(|s| *s = 0);
+      self.clause_stamps.iter_mut().for_each(|s| *s = 0);
+      self.stamp = 0;
+    }
+    self.stamp += 1;
+    let stamp = self.stamp;
+    let mut components = vec![];
+    let mut free = 0;
+    let mut comp_vars = vec![];
+    let mut comp_clauses = vec![];
+    for &root in vars {
+      if self.assignments[root as usize].is_some() || self.var_stamps[root as usize] == stamp {
+        continue;
+      }
+      self.var_stamps[root as usize] = stamp;
+      comp_vars.push(root);
+      let mut i = 0;
+      while let Some(&var) = comp_vars.get(i) {
+        i += 1;
+        for &c in &self.occs[var as usize] {
+          if self.clause_stamps[c as usize] == stamp {
+            continue;
+          }
+          self.clause_stamps[c as usize] = stamp;
+          let clause = self.clauses[c as usize].as_slice(&self.database);
+          if clause
+            .iter()
+            .any(|lit| lit.assn(&self.assignments) == Some(true))
+          {
+            continue;
+          }
+          comp_clauses.push(c);
+          for lit in clause {
+            let v = lit.var() as usize;
+            if self.assignments[v].is_none() && self.var_stamps[v] != stamp {
+              self.var_stamps[v] = stamp;
+              comp_vars.push(lit.var());
+            }
+          }
+        }
+      }
+      if comp_clauses.is_empty() {
+        debug_assert_eq!(comp_vars.len(), 1);
+        free += 1;
+      } else {
+        comp_vars.sort_unstable();
+        comp_clauses.sort_unstable();
+        comp_vars.push(SEPARATOR);
+        comp_vars.append(&mut comp_clauses);
+        components.push(Rc::from(&comp_vars[..]));
+      }
+      comp_vars.clear();
+      comp_clauses.clear();
+    }
+    (components, free)
+  }
+  /// Returns the unassigned variable occurring in the most clauses.
+  fn pick_var(&mut self, clauses: &[u32]) -> u32 {
+    let mut best = None;
+    for &c in clauses {
+      for lit in self.clauses[c as usize].iter(&self.database) {
+        let v = lit.var() as usize;
+        if self.assignments[v].is_some() {
+          continue;
+        }
+        self.scores[v] += 1;
+        let better = match best {
+          None => true,
+          Some(b) => self.scores[v] > self.scores[b as usize],
+        };
+        if better {
+          best = Some(v as u32);
+        }
+      }
+    }
+    for &c in clauses {
+      for lit in self.clauses[c as usize].iter(&self.database) {
+        self.scores[lit.var() as usize] = 0;
+      }
+    }
+    best.expect("Component without unassigned variables")
+  }
+
+  fn assign(&mut self, lit: Literal, cause: Option<CRef>) {
+    let var = lit.var() as usize;
+    debug_assert_eq!(self.assignments[var], None);
+    self.trail.push(lit);
+    self.assignments[var] = Some(lit.val());
+    self.levels[var] = self.level_indeces.len() as u32;
+    self.causes[var] = cause;
+    let units = &mut self.unit_buf;
+    self
+      .watch_list
+      .set(lit, &self.assignments, &self.database, |c, l| {
+        units.push((c, l))
+      });
+  }
+  /// Assigns literals implied by unit clauses, returning a false clause if there is one.
+  fn propagate(&mut self) -> Option<CRef> {
+    while let Some((cause, lit)) = self.unit_buf.pop() {
+      match lit.assn(&self.assignments) {
+        Some(true) => continue,
+        None => self.assign(lit, Some(cause)),
+        Some(false) => {
+          self.unit_buf.clear();
+          return Some(cause);
+        },
+      }
+    }
+    None
+  }
+  /// Unassigns every literal at the current level.
+  fn backtrack(&mut self) {
+    let index = self.level_indeces.pop().unwrap() as usize;
+    for lit in self.trail.drain(index..) {
+      let var = lit.var() as usize;
+      self.assignments[var] = None;
+      self.levels[var] = INVALID_LEVEL;
+      self.causes[var] = None;
+    }
+  }
+  /// Derives a clause from a conflict at the current level with one literal at that level.
+  fn analyze(&mut self, conflict: &CRef) -> Vec<Literal> {
+    let level = self.level_indeces.len() as u32;
+    // the first literal is replaced by the negated unique implication point
+    let mut learnt = vec![Literal::INVALID];
+    let mut clause = *conflict;
+    let mut pending = 0;
+    let mut idx = self.trail.len();
+    let mut uip = None;
+    loop {
+      for &lit in clause.iter(&self.database) {
+        let var = lit.var() as usize;
+        if Some(lit.var()) == uip.map(Literal::var) || self.seen[var] || self.levels[var] == 0 {
+          continue;
+        }
+        self.seen[var] = true;
+        if self.levels[var] == level {
+          pending += 1;
+        } else {
+          learnt.push(lit);
+        }
+      }
+      let lit = loop {
+        idx -= 1;
+        if self.seen[self.trail[idx].var() as usize] {
+          break self.trail[idx];
+        }
+      };
+      self.seen[lit.var() as usize] = false;
+      uip = Some(lit);
+      pending -= 1;
+      if pending == 0 {
+        break;
+      }
+      clause = self.causes[lit.var() as usize].expect("Missing reason while learning");
+    }
+    learnt[0] = !uip.unwrap();
+    for lit in &learnt[1..] {
+      self.seen[lit.var() as usize] = false;
+    }
+    learnt
+  }
+  /// Adds a learnt clause after backtracking from its conflict.
+  fn learn(&mut self, learnt: Vec<Literal>) {
+    if learnt.len() == 1 {
+      // units can only be assigned at level 0, so they wait until the counter is back there
+      self.units.push(learnt[0]);
+      return;
+    }
+    if self.learnts.len() > self.learnt_limit {
+      for cref in self.learnts.drain(..self.learnt_limit / 2) {
+        self.watch_list.unwatch(cref, &self.database);
+      }
+      self.collect_garbage();
+    }
+    let cref = self.database.add_learnt_from_slice(&learnt);
+    self
+      .watch_list
+      .add_learnt(&self.assignments, cref, &self.database);
+    self.learnts.push(cref);
+  }
+  /// Assigns the learnt units at level 0, returning false if the clauses have no models.
+  fn assign_units(&mut self) -> bool {
+    debug_assert!(self.level_indeces.is_empty());
+    while let Some(lit) = self.units.pop() {
+      match lit.assn(&self.assignments) {
+        Some(true) => continue,
+        Some(false) => self.unsat = true,
+        None => {
+          self.assign(lit, None);
+          self.unsat = self.propagate().is_some();
+        },
+      }
+      if self.unsat {
+        return false;
+      }
+    }
+    true
+  }
+  /// Moves the input clauses, the watched learnt clauses, and the reasons of assigned literals
+  /// into a new database, freeing the learnt clauses which were dropped.
+  fn collect_garbage(&mut self) {
+    debug_assert!(self.unit_buf.is_empty());
+    let mut db = Database::new();
+    db.max_var = self.database.max_var;
+    let mut moved: HashMap<CRef, CRef, BuildHasherDefault<FxHasher>> =
+      HashMap::with_hasher(Default::default());
+    let old = &self.database;
+    let mut relocate = |cref: &mut CRef| {
+      let lits = cref.as_slice(old);
+      let learnt = cref.is_learnt();
+      *cref = *moved.entry(*cref).or_insert_with(|| {
+        if learnt {
+          db.add_learnt_from_slice(lits)
+        } else {
+          db.add_clause_from_slice(lits)
+        }
+      });
+    };
+    self.clauses.iter_mut().for_each(&mut relocate);
+    self.learnts.iter_mut().for_each(&mut relocate);
+    for lit in &self.trail {
+      if let Some(cref) = self.causes[lit.var() as usize].as_mut() {
+        relocate(cref);
+      }
+    }
+    self.watch_list.relocate(|cref| moved[&cref]);
+    self.database = db;
+  }
+}
+
+impl Default for Counter {
+  fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    test_util::{models, random_clauses},
+    Rng,
+  };
+
+  fn big(v: u128) -> BigUint {
+    let mut out = BigUint::from((v >> 64) as u64);
+    out <<= 64;
+    out += &BigUint::from(v as u64);
+    out
+  }
+
+  #[test]
+  pub fn test_big_uint_arithmetic() {
+    let mut rng = Rng::new(1);
+    for _ in 0..1000 {
+      // vary the magnitude so that carries both happen and don't
+      let a = rng.next_u64() >> rng.below(64);
+      let b = rng.next_u64() >> rng.below(64);
+      let mut sum = BigUint::from(a);
+      sum += &BigUint::from(b);
+      assert_eq!(sum, big(a as u128 + b as u128));
+      assert_eq!(
+        &BigUint::from(a) * &BigUint::from(b),
+        big(a as u128 * b as u128)
+      );
+      let shift = rng.below(64);
+      let mut shifted = BigUint::from(a);
+      shifted <<= shift;
+      assert_eq!(shifted, big((a as u128) << shift));
+      assert_eq!(shifted.to_string(), ((a as u128) << shift).to_string());
+      assert_eq!(BigUint::from(a).cmp(&BigUint::from(b)), a.cmp(&b));
+    }
+  }
+
+  #[test]
+  pub fn test_big_uint_display() {
+    assert_eq!(BigUint::zero().to_string(), "0");
+    assert_eq!(BigUint::one().to_string(), "1");
+    let mut pow = BigUint::one();
+    pow <<= 100;
+    assert_eq!(pow.to_string(), "1267650600228229401496703205376");
+    assert_eq!(pow.bits(), 101);
+    assert_eq!(pow.log2(), 100.0);
+    let ten = BigUint::from(10);
+    let mut pow = BigUint::one();
+    for _ in 0..30 {
+      pow = &pow * &ten;
+    }
+    assert_eq!(pow.to_string(), format!("1{}", "0".repeat(30)));
+  }
+
+  #[test]
+  pub fn test_count_matches_enumeration() {
+    let mut rng = Rng::new(2);
+    for i in 0..300 {
+      let vars = 1 + i % 12;
+      let num_clauses = rng.below(4 * vars) as usize;
+      let clauses = random_clauses(&mut rng, vars, num_clauses, 4);
+      let mut counter = Counter::new();
+      counter.resize(vars);
+      for clause in &clauses {
+        counter.add_clause(clause);
+      }
+      let expected = models(vars, &clauses).len() as u64;
+      assert_eq!(counter.count(), BigUint::from(expected), "{:?}", clauses);
+    }
+  }
+
+  #[test]
+  pub fn test_count_dropping_learnts() {
+    let mut rng = Rng::new(3);
+    for i in 0..60 {
+      let vars = 10 + i % 6;
+      // clauses of three literals near the satisfiability threshold lead to many conflicts
+      let clauses = (0..4 * vars)
+        .map(|_| {
+          let mut clause = vec![];
+          while clause.len() < 3 {
+            let lit = Literal::new(rng.below(vars), rng.next_bool());
+            if clause.iter().all(|l: &Literal| l.var() != lit.var()) {
+              clause.push(lit);
+            }
+          }
+          clause
+        })
+        .collect::<Vec<_>>();
+      let mut counter = Counter::new();
+      // learnt clauses are dropped often, while some of them are still reasons
+      counter.learnt_limit = 4;
+      counter.resize(vars);
+      for clause in &clauses {
+        counter.add_clause(clause);
+      }
+      let expected = models(vars, &clauses).len() as u64;
+      assert_eq!(counter.count(), BigUint::from(expected), "{:?}", clauses);
+    }
+    // seven pigeons in six holes have no models, but take many conflicts to refute
+    let (pigeons, holes) = (7, 6);
+    let var = |p: u32, h: u32| Literal::new(p * holes + h, false);
+    let mut clauses = (0..pigeons)
+      .map(|p| (0..holes).map(|h| var(p, h)).collect::<Vec<_>>())
+      .collect::<Vec<_>>();
+    for h in 0..holes {
+      for p in 0..pigeons {
+        for q in p + 1..pigeons {
+          clauses.push(vec![!var(p, h), !var(q, h)]);
+        }
+      }
+    }
+    let mut counter = Counter::new();
+    counter.learnt_limit = 4;
+    counter.resize(pigeons * holes);
+    for clause in &clauses {
+      counter.add_clause(clause);
+    }
+    assert_eq!(counter.count(), BigUint::zero());
+    assert!(counter.conflicts > 100);
+    // only the inputs, the kept learnt clauses and the reasons of dropped ones are left
+    let input = clauses.iter().map(Vec::len).sum::<usize>();
+    let vars = (pigeons * holes) as usize;
+    let limit = input + (counter.learnt_limit + 1 + vars) * vars;
+    assert!(counter.database.literals.len() <= limit);
+  }
+
+  #[test]
+  pub fn test_count_trivial() {
+    let mut counter = Counter::new();
+    counter.resize(70);
+    let mut expected = BigUint::one();
+    expected <<= 70;
+    assert_eq!(counter.count(), expected);
+
+    let mut counter = Counter::new();
+    let lit = Literal::new(0, false);
+    counter.add_clause(&[lit]);
+    counter.add_clause(&[!lit]);
+    assert_eq!(counter.count(), BigUint::zero());
+  }
+}
@@ -1,10 +1,15 @@
 use crate::Literal;
-use std::mem::swap;
+use std::{
+  io::{self, Write},
+  mem::swap,
+};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct CRef {
   idx: u32,
   len: u16,
+  /// Whether this clause was learnt, so it is implied by the other clauses
+  learnt: bool,
 }
 
 impl CRef {
@@ -21,12 +26,15 @@ impl CRef {
   }
   pub const fn len(&self) -> usize { self.len as usize }
   pub const fn is_empty(&self) -> bool { self.len == 0 }
+  pub const fn is_learnt(&self) -> bool { self.learnt }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Database {
   /// All clauses in this database
   pub literals: Vec<Literal>,
+  /// Every clause in literals, including ones which are no longer watched
+  clauses: Vec<CRef>,
   swap_space: Vec<Literal>,
   pub max_var: u32,
   pub num_clauses: u32,
@@ -36,6 +44,7 @@ impl Database {
   pub const fn new() -> Self {
     Database {
       literals: vec![],
+      clauses: vec![],
       swap_space: vec![],
       max_var: 0,
       num_clauses: 0,
@@ -43,6 +52,7 @@ impl Database {
   }
   pub fn clear(&mut self) {
     self.literals.clear();
+    self.clauses.clear();
     self.swap_space.clear();
     self.max_var = 0;
     self.num_clauses = 0;
@@ -60,14 +70,58 @@ impl Database {
   }
   */
   #[must_use]
-  pub fn add_clause_from_slice(&mut self, ls: &[Literal]) -> CRef {
+  pub fn add_clause_from_slice(&mut self, ls: &[Literal]) -> CRef { self.add(ls, false) }
+  /// Adds a clause which is implied by the other clauses.
+  #[must_use]
+  pub fn add_learnt_from_slice(&mut self, ls: &[Literal]) -> CRef { self.add(ls, true) }
+  fn add(&mut self, ls: &[Literal], learnt: bool) -> CRef {
     self.num_clauses += 1;
     let idx = self.literals.len() as u32;
     self.literals.extend_from_slice(ls);
-    CRef {
+    let cref = CRef {
       idx,
       len: ls.len() as u16,
+      learnt,
+    };
+    self.clauses.push(cref);
+    cref
+  }
+
+  /// Writes every clause in this database as DIMACs, optionally including learnt clauses.
+  /// A solver stops watching clauses without removing them until it compacts its database, so
+  /// its clauses should be written with `Solver::dump_dimacs` instead.
+  pub fn write_dimacs(&self, w: &mut impl Write, include_learnts: bool) -> io::Result<()> {
+    self.write_dimacs_with_units(w, include_learnts, &[])
+  }
+  /// Writes every clause in this database as DIMACs along with extra unit clauses.
+  pub fn write_dimacs_with_units(
+    &self,
+    w: &mut impl Write,
+    include_learnts: bool,
+    units: &[Literal],
+  ) -> io::Result<()> {
+    let clauses = || {
+      self
+        .clauses
+        .iter()
+        .filter(move |cref| include_learnts || !cref.learnt)
+    };
+    let max_var = units
+      .iter()
+      .chain(clauses().flat_map(|cref| cref.iter(self)))
+      .map(|lit| lit.var() + 1)
+      .fold(self.max_var, u32::max);
+    writeln!(w, "p cnf {} {}", max_var, units.len() + clauses().count())?;
+    for unit in units {
+      writeln!(w, "{} 0", unit)?;
+    }
+    for cref in clauses() {
+      for lit in cref.iter(self) {
+        write!(w, "{} ", lit)?;
+      }
+      writeln!(w, "0")?;
     }
+    Ok(())
   }
 
   /// Removes satisfied clauses at level 0.
@@ -79,6 +133,7 @@ impl Database {
     swap(&mut self.literals, &mut self.swap_space);
     self.num_clauses = 1;
     self.literals.clear();
+    self.clauses.clear();
     clauses.filter_map(move |c| {
       if !self.swap_space[c.idx as usize].is_valid() {
         return None;
@@ -96,18 +151,21 @@ impl Database {
       self.literals.extend(lits);
       let len = (self.literals.len() as u32 - idx) as u16;
       self.swap_space[c.idx as usize] = Literal::INVALID;
+      let learnt = c.learnt;
       if len == 0 {
         None
       } else if len == 1 {
         self.num_clauses += 1;
-        let cref = CRef { idx, len };
+        let cref = CRef { idx, len, learnt };
+        self.clauses.push(cref);
         let mut lits = cref.iter(&self).copied();
         let l_0 = lits.next().unwrap();
         debug_assert!(l_0.is_valid());
         Some((cref, l_0, Literal::INVALID))
       } else {
         self.num_clauses += 1;
-        let cref = CRef { idx, len };
+        let cref = CRef { idx, len, learnt };
+        self.clauses.push(cref);
         let mut lits = cref.iter(&self).copied();
         let l_0 = lits.next().unwrap();
         let l_1 = lits.next().unwrap();
@@ -0,0 +1,641 @@
+use crate::{Database, Literal, Solver};
+
+/// Groups of at most this many literals share a commander in the commander encoding
+pub const COMMANDER_GROUP: usize = 3;
+/// At most one of this many literals is encoded pairwise by the product encoding
+pub const PRODUCT_BASE: usize = 4;
+
+/// Clauses which encodings add new variables and clauses to.
+/// Variables of a `Solver` given to an encoding must be frozen if it has preprocessed.
+pub trait Cnf {
+  /// Returns a variable which does not occur in any clause yet.
+  fn new_var(&mut self) -> u32;
+  /// Adds a clause which every solution must satisfy.
+  fn add_clause(&mut self, lits: &[Literal]);
+  /// Returns the positive literal of a new variable.
+  fn new_lit(&mut self) -> Literal { Literal::new(self.new_var(), false) }
+}
+
+impl Cnf for Solver {
+  fn new_var(&mut self) -> u32 { Solver::new_var(self) }
+  fn add_clause(&mut self, lits: &[Literal]) { Solver::add_clause(self, lits); }
+}
+
+impl Cnf for Database {
+  fn new_var(&mut self) -> u32 {
+    self.max_var += 1;
+    self.max_var - 1
+  }
+  fn add_clause(&mut self, lits: &[Literal]) {
+    let max_var = lits.iter().map(|lit| lit.var() + 1).max().unwrap_or(0);
+    self.max_var = self.max_var.max(max_var);
+    let _ = self.add_clause_from_slice(lits);
+  }
+}
+
+/// Encodings of at most one literal being true.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtMostOne {
+  /// A binary clause for each pair of literals, without new variables
+  Pairwise,
+  /// A ladder of new variables, each true once any earlier literal is (Sinz 2005)
+  Sequential,
+  /// Small groups of literals, each with a commander which is true if any of them is, and at
+  /// most one commander true (Klieber and Kwon 2007)
+  Commander,
+  /// Literals in a grid, where at most one row and one column may have a true literal
+  /// (Chen 2010)
+  Product,
+}
+
+/// Encodings of at most or at least k literals being true.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cardinality {
+  /// Registers for each literal, counting up to k true literals so far (Sinz 2005)
+  SequentialCounter,
+  /// A tree of sums in unary (Bailleux and Boufkhad 2003)
+  Totalizer,
+  /// Networks sorting the literals by merging sorted sequences of k (Asín et al. 2011)
+  Network,
+}
+
+/// Adds clauses so that at most one of lits is true.
+pub fn at_most_one(cnf: &mut impl Cnf, lits: &[Literal], encoding: AtMostOne) {
+  match encoding {
+    AtMostOne::Pairwise => pairwise(cnf, lits),
+    AtMostOne::Sequential => sequential(cnf, lits),
+    AtMostOne::Commander => commander(cnf, lits),
+    AtMostOne::Product => product(cnf, lits),
+  }
+}
+
+/// Adds clauses so that exactly one of lits is true.
+pub fn exactly_one(cnf: &mut impl Cnf, lits: &[Literal], encoding: AtMostOne) {
+  if lits.is_empty() {
+    return contradiction(cnf);
+  }
+  cnf.add_clause(lits);
+  at_most_one(cnf, lits, encoding);
+}
+
+/// Adds clauses so that at most k of lits are true.
+pub fn at_most_k(cnf: &mut impl Cnf, lits: &[Literal], k: usize, encoding: Cardinality) {
+  if k >= lits.len() {
+    return;
+  } else if k == 0 {
+    for &lit in lits {
+      cnf.add_clause(&[!lit]);
+    }
+    return;
+  }
+  let too_many = match encoding {
+    Cardinality::SequentialCounter => return sequential_counter(cnf, lits, k),
+    Cardinality::Totalizer => Totalizer::new(lits).at_least(cnf, k + 1).unwrap(),
+    Cardinality::Network => cardinality_network(cnf, lits, k + 1)[k],
+  };
+  cnf.add_clause(&[!too_many]);
+}
+
+/// Adds clauses so that at least k of lits are true.
+pub fn at_least_k(cnf: &mut impl Cnf, lits: &[Literal], k: usize, encoding: Cardinality) {
+  if k > lits.len() {
+    return contradiction(cnf);
+  }
+  // at most the rest are false
+  let negated = lits.iter().map(|&lit| !lit).collect::<Vec<_>>();
+  at_most_k(cnf, &negated, lits.len() - k, encoding);
+}
+
+/// Adds clauses so that exactly k of lits are true.
+pub fn exactly_k(cnf: &mut impl Cnf, lits: &[Literal], k: usize, encoding: Cardinality) {
+  at_most_k(cnf, lits, k, encoding);
+  at_least_k(cnf, lits, k, encoding);
+}
+
+/// Adds a unit clause and its negation, as an empty clause cannot be stored by every `Cnf`.
+fn contradiction(cnf: &mut impl Cnf) {
+  let lit = cnf.new_lit();
+  cnf.add_clause(&[lit]);
+  cnf.add_clause(&[!lit]);
+}
+
+fn pairwise(cnf: &mut impl Cnf, lits: &[Literal]) {
+  for (i, &a) in lits.iter().enumerate() {
+    for &b in &lits[i + 1..] {
+      cnf.add_clause(&[!a, !b]);
+    }
+  }
+}
+
+fn sequential(cnf: &mut impl Cnf, lits: &[Literal]) {
+  let (&last, lits) = match lits.split_last() {
+    Some(split) if !split.1.is_empty() => split,
+    _ => return,
+  };
+  // each is true if any literal up to it is
+  let mut prev: Option<Literal> = None;
+  for &lit in lits {
+    let seen = cnf.new_lit();
+    cnf.add_clause(&[!lit, seen]);
+    if let Some(prev) = prev {
+      cnf.add_clause(&[!prev, seen]);
+      cnf.add_clause(&[!prev, !lit]);
+    }
+    prev = Some(seen);
+  }
+  cnf.add_clause(&[!prev.unwrap(), !last]);
+}
+
+fn commander(cnf: &mut impl Cnf, lits: &[Literal]) {
+  if lits.len() <= COMMANDER_GROUP {
+    return pairwise(cnf, lits);
+  }
+  let commanders = lits
+    .chunks(COMMANDER_GROUP)
+    .map(|group| {
+      if let [lit] = group[..] {
+        return lit;
+      }
+      pairwise(cnf, group);
+      let commander = cnf.new_lit();
+      for &lit in group {
+        cnf.add_clause(&[!lit, commander]);
+      }
+      commander
+    })
+    .collect::<Vec<_>>();
+  commander(cnf, &commanders);
+}
+
+fn product(cnf: &mut impl Cnf, lits: &[Literal]) {
+  if lits.len() <= PRODUCT_BASE {
+    return pairwise(cnf, lits);
+  }
+  let mut rows = 1;
+  while rows * rows < lits.len() {
+    rows += 1;
+  }
+  let mut cols = 1;
+  while rows * cols < lits.len() {
+    cols += 1;
+  }
+  let row_lits = (0..rows).map(|_| cnf.new_lit()).collect::<Vec<_>>();
+  let col_lits = (0..cols).map(|_| cnf.new_lit()).collect::<Vec<_>>();
+  for (i, &lit) in lits.iter().enumerate() {
+    cnf.add_clause(&[!lit, row_lits[i / cols]]);
+    cnf.add_clause(&[!lit, col_lits[i % cols]]);
+  }
+  product(cnf, &row_lits);
+  product(cnf, &col_lits);
+}
+
+/// Encodes at most k of lits being true, where 0 < k < lits.len().
+fn sequential_counter(cnf: &mut impl Cnf, lits: &[Literal], k: usize) {
+  let (&last, lits) = lits.split_last().unwrap();
+  // prev[j] is true if at least j + 1 literals up to the previous one are true
+  let mut prev: Vec<Literal> = vec![];
+  for &lit in lits {
+    let counts = (0..k).map(|_| cnf.new_lit()).collect::<Vec<_>>();
+    cnf.add_clause(&[!lit, counts[0]]);
+    if !prev.is_empty() {
+      for j in 0..k {
+        cnf.add_clause(&[!prev[j], counts[j]]);
+        if j > 0 {
+          cnf.add_clause(&[!lit, !prev[j - 1], counts[j]]);
+        }
+      }
+      cnf.add_clause(&[!lit, !prev[k - 1]]);
+    }
+    prev = counts;
+  }
+  cnf.add_clause(&[!last, !prev[k - 1]]);
+}
+
+/// Returns outputs of a cardinality network over lits, where the output at i is implied by at
+/// least i + 1 of lits being true, for i < k.
+fn cardinality_network(cnf: &mut impl Cnf, lits: &[Literal], k: usize) -> Vec<Literal> {
+  // networks need a multiple of a power of two inputs, so they are padded with a false literal
+  let k = k.next_power_of_two();
+  let mut inputs = lits.to_vec();
+  let extra = inputs.len() % k;
+  if extra > 0 {
+    let padding = cnf.new_lit();
+    cnf.add_clause(&[!padding]);
+    inputs.resize(inputs.len() + k - extra, padding);
+  }
+  card(cnf, &inputs, k)
+}
+
+/// Returns the k largest of lits, whose length is a multiple of k, sorted.
+fn card(cnf: &mut impl Cnf, lits: &[Literal], k: usize) -> Vec<Literal> {
+  if lits.len() == k {
+    return sort(cnf, lits);
+  }
+  let (first, rest) = lits.split_at(k);
+  let first = card(cnf, first, k);
+  let rest = card(cnf, rest, k);
+  let mut merged = simplified_merge(cnf, &first, &rest);
+  merged.truncate(k);
+  merged
+}
+
+/// Sorts lits, whose length is a power of two.
+fn sort(cnf: &mut impl Cnf, lits: &[Literal]) -> Vec<Literal> {
+  if lits.len() == 1 {
+    return lits.to_vec();
+  }
+  let (a, b) = lits.split_at(lits.len() / 2);
+  let a = sort(cnf, a);
+  let b = sort(cnf, b);
+  merge(cnf, &a, &b)
+}
+
+/// Merges two sorted sequences of the same length, which is a power of two.
+fn merge(cnf: &mut impl Cnf, a: &[Literal], b: &[Literal]) -> Vec<Literal> {
+  if let ([a], [b]) = (a, b) {
+    let (max, min) = comparator(cnf, *a, *b);
+    return vec![max, min];
+  }
+  let odd = merge(cnf, &evens(a), &evens(b));
+  let even = merge(cnf, &odds(a), &odds(b));
+  let mut merged = vec![odd[0]];
+  for i in 1..odd.len() {
+    let (max, min) = comparator(cnf, odd[i], even[i - 1]);
+    merged.push(max);
+    merged.push(min);
+  }
+  merged.push(*even.last().unwrap());
+  merged
+}
+
+/// Merges two sorted sequences of the same length n, which is a power of two, keeping only
+/// the n + 1 largest.
+fn simplified_merge(cnf: &mut impl Cnf, a: &[Literal], b: &[Literal]) -> Vec<Literal> {
+  if let ([a], [b]) = (a, b) {
+    let (max, min) = comparator(cnf, *a, *b);
+    return vec![max, min];
+  }
+  let odd = simplified_merge(cnf, &evens(a), &evens(b));
+  let even = simplified_merge(cnf, &odds(a), &odds(b));
+  let mut merged = vec![odd[0]];
+  for i in 1..odd.len() {
+    let (max, min) = comparator(cnf, odd[i], even[i - 1]);
+    merged.push(max);
+    merged.push(min);
+  }
+  merged
+}
+
+/// Returns the literals at even indices, which are the odd ones counting from 1.
+fn evens(lits: &[Literal]) -> Vec<Literal> { lits.iter().step_by(2).copied().collect() }
+fn odds(lits: &[Literal]) -> Vec<Literal> { lits.iter().skip(1).step_by(2).copied().collect() }
+
+/// Returns new literals which are implied by the larger and smaller of a and b.
+fn comparator(cnf: &mut impl Cnf, a: Literal, b: Literal) -> (Literal, Literal) {
+  let (max, min) = (cnf.new_lit(), cnf.new_lit());
+  cnf.add_clause(&[!a, max]);
+  cnf.add_clause(&[!b, max]);
+  if a == b {
+    cnf.add_clause(&[!a, min]);
+  } else {
+    cnf.add_clause(&[!a, !b, min]);
+  }
+  (max, min)
+}
+
+/// Totalizer (Bailleux and Boufkhad 2003) over some inputs, with outputs which are implied
+/// by at least as many inputs being true. Outputs are only added up to the largest bound
+/// asked for, so a bound which is raised over time only adds what it needs.
+#[derive(Debug)]
+pub struct Totalizer {
+  /// Nodes of the tree with children before their parents, so the root is last
+  nodes: Vec<TotalizerNode>,
+}
+
+#[derive(Debug)]
+struct TotalizerNode {
+  /// outputs[i] is implied by at least i + 1 inputs under this node being true
+  outputs: Vec<Literal>,
+  /// Number of inputs under this node
+  size: usize,
+  children: Option<(usize, usize)>,
+}
+
+impl Totalizer {
+  pub fn new(inputs: &[Literal]) -> Self {
+    assert!(!inputs.is_empty(), "Totalizer over no inputs");
+    let mut totalizer = Self { nodes: vec![] };
+    totalizer.build(inputs);
+    totalizer
+  }
+  fn build(&mut self, inputs: &[Literal]) -> usize {
+    let node = if let [input] = inputs[..] {
+      TotalizerNode {
+        outputs: vec![input],
+        size: 1,
+        children: None,
+      }
+    } else {
+      let (left, right) = inputs.split_at(inputs.len() / 2);
+      TotalizerNode {
+        outputs: vec![],
+        size: inputs.len(),
+        children: Some((self.build(left), self.build(right))),
+      }
+    };
+    self.nodes.push(node);
+    self.nodes.len() - 1
+  }
+  /// Returns the output implied by at least k inputs being true, adding it if needed, or None
+  /// if there are fewer than k inputs.
+  pub fn at_least(&mut self, cnf: &mut impl Cnf, k: usize) -> Option<Literal> {
+    let root = self.nodes.len() - 1;
+    if k == 0 || k > self.nodes[root].size {
+      return None;
+    }
+    self.extend(cnf, root, k);
+    Some(self.nodes[root].outputs[k - 1])
+  }
+  /// Adds outputs to a node and its children until it has k of them.
+  fn extend(&mut self, cnf: &mut impl Cnf, node: usize, k: usize) {
+    let (left, right) = match self.nodes[node].children {
+      None => return,
+      Some(children) => children,
+    };
+    let k = k.min(self.nodes[node].size);
+    let old = self.nodes[node].outputs.len();
+    if k <= old {
+      return;
+    }
+    self.extend(cnf, left, k);
+    self.extend(cnf, right, k);
+    for _ in old..k {
+      let out = cnf.new_lit();
+      self.nodes[node].outputs.push(out);
+    }
+    // earlier outputs of the children were already combined into earlier outputs of the node
+    let (l, r) = (&self.nodes[left].outputs, &self.nodes[right].outputs);
+    let outputs = &self.nodes[node].outputs;
+    let mut clause = vec![];
+    for i in 0..=l.len() {
+      for j in 0..=r.len() {
+        if i + j <= old || i + j > k {
+          continue;
+        }
+        clause.clear();
+        if i > 0 {
+          clause.push(!l[i - 1]);
+        }
+        if j > 0 {
+          clause.push(!r[j - 1]);
+        }
+        clause.push(outputs[i + j - 1]);
+        cnf.add_clause(&clause);
+      }
+    }
+  }
+}
+
+/// Generalized totalizer (Joshi et al. 2015) over weighted inputs, with outputs which are
+/// implied by the total weight of the true inputs reaching their value. Sums above a limit are
+/// all given by the output at the limit, which keeps the number of outputs below it.
+#[derive(Debug)]
+pub struct GeneralizedTotalizer {
+  /// Each distinct sum of weights up to the limit with its output, in increasing order
+  outputs: Vec<(u64, Literal)>,
+}
+
+impl GeneralizedTotalizer {
+  pub fn new(cnf: &mut impl Cnf, inputs: &[(Literal, u64)], limit: u64) -> Self {
+    assert!(!inputs.is_empty(), "Totalizer over no inputs");
+    Self {
+      outputs: Self::build(cnf, inputs, limit),
+    }
+  }
+  /// Returns each distinct sum of weights up to the limit with its output, in increasing order.
+  pub fn outputs(&self) -> &[(u64, Literal)] { &self.outputs }
+  /// Returns the outputs of a node over some inputs, after adding the clauses defining them.
+  fn build(cnf: &mut impl Cnf, inputs: &[(Literal, u64)], limit: u64) -> Vec<(u64, Literal)> {
+    if let [(lit, weight)] = inputs[..] {
+      return vec![(weight.min(limit), lit)];
+    }
+    let (left, right) = inputs.split_at(inputs.len() / 2);
+    let left = Self::build(cnf, left, limit);
+    let right = Self::build(cnf, right, limit);
+    let add = |a: u64, b: u64| a.saturating_add(b).min(limit);
+    let mut sums = left
+      .iter()
+      .chain(right.iter())
+      .map(|&(sum, _)| sum)
+      .chain(
+        left
+          .iter()
+          .flat_map(|&(a, _)| right.iter().map(move |&(b, _)| add(a, b))),
+      )
+      .collect::<Vec<_>>();
+    sums.sort_unstable();
+    sums.dedup();
+    let outputs = sums
+      .into_iter()
+      .map(|sum| (sum, cnf.new_lit()))
+      .collect::<Vec<_>>();
+    let output = |sum: u64| {
+      let i = outputs.binary_search_by_key(&sum, |&(s, _)| s).unwrap();
+      outputs[i].1
+    };
+    for &(sum, lit) in left.iter().chain(right.iter()) {
+      cnf.add_clause(&[!lit, output(sum)]);
+    }
+    for &(a, l) in &left {
+      for &(b, r) in &right {
+        cnf.add_clause(&[!l, !r, output(add(a, b))]);
+      }
+    }
+    outputs
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{test_util::dimacs_clauses, Rng};
+
+  const AT_MOST_ONE: [AtMostOne; 4] = [
+    AtMostOne::Pairwise,
+    AtMostOne::Sequential,
+    AtMostOne::Commander,
+    AtMostOne::Product,
+  ];
+  const CARDINALITY: [Cardinality; 3] = [
+    Cardinality::SequentialCounter,
+    Cardinality::Totalizer,
+    Cardinality::Network,
+  ];
+
+  /// Returns n literals over the first n variables, with mixed polarities.
+  fn lits(n: usize) -> Vec<Literal> {
+    (0..n as u32)
+      .map(|var| Literal::new(var, var % 3 == 1))
+      .collect()
+  }
+
+  /// Assumes every assignment to lits, checking that it extends to a solution exactly when ok
+  /// holds for the number of true literals.
+  fn check(solver: &mut Solver, lits: &[Literal], ok: impl Fn(usize) -> bool) {
+    for bits in 0..1u32 << lits.len() {
+      let assumptions = lits
+        .iter()
+        .enumerate()
+        .map(|(i, &lit)| if bits >> i & 1 == 1 { lit } else { !lit })
+        .collect::<Vec<_>>();
+      let count = bits.count_ones() as usize;
+      assert_eq!(
+        solver.solve_with_assumptions(&assumptions),
+        ok(count),
+        "{}",
+        count
+      );
+    }
+  }
+
+  /// Encodes a constraint into a solver, checking it against counting true literals.
+  fn check_encoding(
+    n: usize,
+    ok: impl Fn(usize) -> bool,
+    encode: impl Fn(&mut Solver, &[Literal]),
+  ) {
+    let lits = lits(n);
+    let mut solver = Solver::new();
+    solver.resize(n as u32);
+    for lit in &lits {
+      solver.set_frozen(lit.var(), true);
+    }
+    encode(&mut solver, &lits);
+    check(&mut solver, &lits, ok);
+  }
+
+  #[test]
+  pub fn test_at_most_one() {
+    for n in 0..8 {
+      for &encoding in &AT_MOST_ONE {
+        check_encoding(n, |c| c <= 1, |s, l| at_most_one(s, l, encoding));
+        check_encoding(n, |c| c == 1, |s, l| exactly_one(s, l, encoding));
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_cardinality() {
+    for n in 0..7 {
+      for k in 0..=n + 1 {
+        for &encoding in &CARDINALITY {
+          check_encoding(n, |c| c <= k, |s, l| at_most_k(s, l, k, encoding));
+          check_encoding(n, |c| c >= k, |s, l| at_least_k(s, l, k, encoding));
+          check_encoding(n, |c| c == k, |s, l| exactly_k(s, l, k, encoding));
+        }
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_database() {
+    for n in 0..6 {
+      for k in 0..=n + 1 {
+        for &encoding in &CARDINALITY {
+          let lits = lits(n);
+          let mut db = Database::new();
+          db.max_var = n as u32;
+          exactly_k(&mut db, &lits, k, encoding);
+          let mut dimacs = vec![];
+          db.write_dimacs(&mut dimacs, false).unwrap();
+          let mut solver = Solver::new();
+          solver.resize(db.max_var);
+          for lit in &lits {
+            solver.set_frozen(lit.var(), true);
+          }
+          // infeasible constraints must not store an empty clause
+          for clause in dimacs_clauses(&dimacs) {
+            assert!(!clause.is_empty());
+            assert!(clause.iter().all(|lit| lit.var() < db.max_var));
+            solver.add_clause(&clause);
+          }
+          check(&mut solver, &lits, |c| c == k);
+        }
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_totalizer_bounds() {
+    let mut rng = Rng::new(1);
+    for n in 1..7 {
+      let lits = lits(n);
+      let mut solver = Solver::new();
+      solver.resize(n as u32);
+      let mut totalizer = Totalizer::new(&lits);
+      // outputs are added as larger bounds are asked for
+      for _ in 0..4 {
+        let k = rng.below(n as u32 + 2) as usize;
+        let out = match totalizer.at_least(&mut solver, k) {
+          None => {
+            assert!(k == 0 || k > n);
+            continue;
+          },
+          Some(out) => out,
+        };
+        solver.set_frozen(out.var(), true);
+        for bits in 0..1u32 << n {
+          let mut assumptions = lits
+            .iter()
+            .enumerate()
+            .map(|(i, &lit)| if bits >> i & 1 == 1 { lit } else { !lit })
+            .collect::<Vec<_>>();
+          assumptions.push(!out);
+          let count = bits.count_ones() as usize;
+          assert_eq!(solver.solve_with_assumptions(&assumptions), count < k);
+        }
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_generalized_totalizer() {
+    let mut rng = Rng::new(2);
+    for n in 1..7 {
+      let inputs = lits(n)
+        .into_iter()
+        .map(|lit| (lit, 1 + rng.below(5) as u64))
+        .collect::<Vec<_>>();
+      let limit = 1 + rng.below(15) as u64;
+      let mut solver = Solver::new();
+      solver.resize(n as u32);
+      let totalizer = GeneralizedTotalizer::new(&mut solver, &inputs, limit);
+      let outputs = totalizer.outputs();
+      assert!(outputs.windows(2).all(|w| w[0].0 < w[1].0));
+      assert!(outputs.iter().all(|&(sum, _)| sum <= limit));
+      for bits in 0..1u32 << n {
+        let mut assumptions = vec![];
+        let mut weight = 0;
+        for (i, &(lit, w)) in inputs.iter().enumerate() {
+          if bits >> i & 1 == 1 {
+            assumptions.push(lit);
+            weight += w;
+          } else {
+            assumptions.push(!lit);
+          }
+        }
+        // bounding the sum below some value is possible exactly when the true inputs weigh less
+        for &(bound, _) in outputs {
+          let mut bounded = assumptions.clone();
+          bounded.extend(
+            outputs
+              .iter()
+              .filter(|&&(sum, _)| sum >= bound)
+              .map(|&(_, lit)| !lit),
+          );
+          assert_eq!(solver.solve_with_assumptions(&bounded), weight < bound);
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,474 @@
+use crate::{Rng, VariableState};
+use std::fmt::Debug;
+
+/// A branching heuristic, which decides which variable the solver should pick next.
+/// The solver notifies it of assignments and of which variables took part in each conflict.
+pub trait DecisionHeuristic: Debug {
+  /// Called once after each conflict has been analyzed.
+  fn decay(&mut self);
+  /// Called for each variable which participated in deriving a learnt clause.
+  fn increase_var_activity(&mut self, var: u32);
+  /// Called for each variable in the reason of a learnt literal, but not in the clause itself.
+  fn reasoned(&mut self, _var: u32) {}
+  /// Whether `reasoned` should be called, as finding reason-side variables is not free.
+  fn uses_reasons(&self) -> bool { false }
+  /// Called each time a variable is assigned by either a decision or propagation.
+  fn assigned(&mut self, _var: u32) {}
+  /// Called when a variable is unassigned, so that it can be picked again.
+  fn enable(&mut self, var: u32);
+  /// returns the variable with highest priority, or None if every variable has been taken.
+  /// Modifies the internal state so that the variable cannot be picked again
+  /// Until it is re-enabled
+  fn take_highest_prio(&mut self) -> Option<u32>;
+  /// Returns the variable take_highest_prio would return, without taking it.
+  fn peek_highest_prio(&mut self) -> Option<u32>;
+  /// Returns the current score of this variable.
+  fn activity(&self, var: u32) -> f32;
+  /// Overwrites the score of this variable, used for user supplied decision priorities.
+  fn set_activity(&mut self, var: u32, act: f32);
+  fn clear(&mut self);
+  fn resize(&mut self, vars: u32);
+}
+
+/// Which decision heuristic a solver should use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Heuristic {
+  /// Variable State Independent Decaying Sum, as in Minisat.
+  Vsids,
+  /// Conflict History-Based branching.
+  Chb,
+  /// Learning Rate Branching.
+  Lrb,
+  /// Variable Move To Front.
+  Vmtf,
+}
+
+impl Heuristic {
+  /// Creates an empty instance of this heuristic.
+  /// If a seed is given, initial scores are randomized for heuristics which keep scores.
+  pub fn build(self, seed: Option<u64>) -> Box<dyn DecisionHeuristic> {
+    let order = || {
+      let mut order = VariableState::new();
+      order.random_init = seed.map(Rng::new);
+      order
+    };
+    match self {
+      Heuristic::Vsids => Box::new(order()),
+      Heuristic::Chb => Box::new(Chb::with_order(order())),
+      Heuristic::Lrb => Box::new(Lrb::with_order(order())),
+      Heuristic::Vmtf => Box::new(Vmtf::new()),
+    }
+  }
+}
+
+impl DecisionHeuristic for VariableState {
+  fn decay(&mut self) { VariableState::decay(self) }
+  fn increase_var_activity(&mut self, var: u32) { VariableState::increase_var_activity(self, var) }
+  fn enable(&mut self, var: u32) { VariableState::enable(self, var) }
+  fn take_highest_prio(&mut self) -> Option<u32> { VariableState::take_highest_prio(self) }
+  fn peek_highest_prio(&mut self) -> Option<u32> { VariableState::peek_highest_prio(self) }
+  fn activity(&self, var: u32) -> f32 { VariableState::activity(self, var) }
+  fn set_activity(&mut self, var: u32, act: f32) { VariableState::set_activity(self, var, act) }
+  fn clear(&mut self) { VariableState::clear(self) }
+  fn resize(&mut self, vars: u32) { VariableState::resize(self, vars) }
+}
+
+pub const DEFAULT_STEP_SIZE: f32 = 0.4;
+pub const DEFAULT_STEP_SIZE_DEC: f32 = 1e-6;
+pub const DEFAULT_MIN_STEP_SIZE: f32 = 0.06;
+
+/// Multiplier for rewards of variables assigned by a propagation which did not lead to a
+/// conflict.
+const CHB_NO_CONFLICT_MULT: f32 = 0.9;
+
+/// Conflict History-Based branching (Liang et al. 2016).
+/// Rewards assigned variables by how recently they were involved in conflicts, using an
+/// exponential recency weighted average.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chb {
+  /// Ordering of variables by their current score.
+  order: VariableState,
+  /// Which conflict was each variable last involved in.
+  last_conflict: Vec<u64>,
+  conflicts: u64,
+  /// Variables assigned since the last reward, which are rewarded once it is known whether
+  /// their propagation led to a conflict.
+  assigned: Vec<u32>,
+
+  /// Current step size of the weighted average
+  pub step_size: f32,
+  /// How much the step size is decreased on each conflict
+  pub step_size_dec: f32,
+  /// Lower bound on the step size
+  pub min_step_size: f32,
+}
+
+impl Chb {
+  pub fn new() -> Self { Self::with_order(VariableState::new()) }
+  /// Creates an instance which orders variables using an empty VariableState.
+  pub fn with_order(order: VariableState) -> Self {
+    Self {
+      order,
+      last_conflict: vec![],
+      conflicts: 0,
+      assigned: vec![],
+      step_size: DEFAULT_STEP_SIZE,
+      step_size_dec: DEFAULT_STEP_SIZE_DEC,
+      min_step_size: DEFAULT_MIN_STEP_SIZE,
+    }
+  }
+  fn reward(&mut self, var: u32, multiplier: f32) {
+    let since = self.conflicts - self.last_conflict[var as usize] + 1;
+    let reward = multiplier / (since as f32);
+    let q = self.order.activity(var);
+    let alpha = self.step_size;
+    self
+      .order
+      .set_activity(var, (1.0 - alpha) * q + alpha * reward);
+  }
+  /// Rewards every variable assigned since the last reward.
+  fn reward_assigned(&mut self, multiplier: f32) {
+    for var in std::mem::take(&mut self.assigned) {
+      self.reward(var, multiplier);
+    }
+  }
+}
+
+impl DecisionHeuristic for Chb {
+  fn decay(&mut self) {
+    self.conflicts += 1;
+    self.step_size = (self.step_size - self.step_size_dec).max(self.min_step_size);
+  }
+  fn increase_var_activity(&mut self, var: u32) {
+    // the propagation before analysis led to a conflict
+    self.reward_assigned(1.0);
+    self.last_conflict[var as usize] = self.conflicts;
+  }
+  fn assigned(&mut self, var: u32) { self.assigned.push(var) }
+  // Deciding or backtracking before a conflict is analyzed means the propagation finished
+  // without one.
+  fn enable(&mut self, var: u32) {
+    self.reward_assigned(CHB_NO_CONFLICT_MULT);
+    self.order.enable(var)
+  }
+  fn take_highest_prio(&mut self) -> Option<u32> {
+    self.reward_assigned(CHB_NO_CONFLICT_MULT);
+    self.order.take_highest_prio()
+  }
+  fn peek_highest_prio(&mut self) -> Option<u32> {
+    self.reward_assigned(CHB_NO_CONFLICT_MULT);
+    self.order.peek_highest_prio()
+  }
+  fn activity(&self, var: u32) -> f32 { self.order.activity(var) }
+  fn set_activity(&mut self, var: u32, act: f32) { self.order.set_activity(var, act) }
+  fn clear(&mut self) {
+    self.order.clear();
+    self.last_conflict.clear();
+    self.conflicts = 0;
+    self.assigned.clear();
+    self.step_size = DEFAULT_STEP_SIZE;
+  }
+  fn resize(&mut self, vars: u32) {
+    self.order.resize(vars);
+    self.last_conflict.resize(vars as usize, 0);
+  }
+}
+
+impl Default for Chb {
+  fn default() -> Self { Self::new() }
+}
+
+/// Learning Rate Branching (Liang et al. 2016), with the reason side extension.
+/// Scores variables by how many conflicts they participated in while they were assigned.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lrb {
+  /// Ordering of variables by their current score.
+  order: VariableState,
+  /// Which conflict was each variable assigned at
+  assigned_at: Vec<u64>,
+  /// How many learnt clauses each variable participated in since it was assigned
+  participated: Vec<u32>,
+  /// How many learnt clauses each variable was in the reason side of since it was assigned
+  reasoned: Vec<u32>,
+  conflicts: u64,
+
+  /// Current step size of the weighted average
+  pub step_size: f32,
+  /// How much the step size is decreased on each conflict
+  pub step_size_dec: f32,
+  /// Lower bound on the step size
+  pub min_step_size: f32,
+}
+
+impl Lrb {
+  pub fn new() -> Self { Self::with_order(VariableState::new()) }
+  /// Creates an instance which orders variables using an empty VariableState.
+  pub fn with_order(order: VariableState) -> Self {
+    Self {
+      order,
+      assigned_at: vec![],
+      participated: vec![],
+      reasoned: vec![],
+      conflicts: 0,
+      step_size: DEFAULT_STEP_SIZE,
+      step_size_dec: DEFAULT_STEP_SIZE_DEC,
+      min_step_size: DEFAULT_MIN_STEP_SIZE,
+    }
+  }
+}
+
+impl DecisionHeuristic for Lrb {
+  fn decay(&mut self) {
+    self.conflicts += 1;
+    self.step_size = (self.step_size - self.step_size_dec).max(self.min_step_size);
+  }
+  fn increase_var_activity(&mut self, var: u32) { self.participated[var as usize] += 1; }
+  fn reasoned(&mut self, var: u32) { self.reasoned[var as usize] += 1; }
+  fn uses_reasons(&self) -> bool { true }
+  fn assigned(&mut self, var: u32) {
+    let var = var as usize;
+    self.assigned_at[var] = self.conflicts;
+    self.participated[var] = 0;
+    self.reasoned[var] = 0;
+  }
+  fn enable(&mut self, var: u32) {
+    let i = var as usize;
+    let interval = self.conflicts - self.assigned_at[i];
+    if interval > 0 {
+      let reward = (self.participated[i] + self.reasoned[i]) as f32 / interval as f32;
+      let q = self.order.activity(var);
+      let alpha = self.step_size;
+      self
+        .order
+        .set_activity(var, (1.0 - alpha) * q + alpha * reward);
+    }
+    self.order.enable(var)
+  }
+  fn take_highest_prio(&mut self) -> Option<u32> { self.order.take_highest_prio() }
+  fn peek_highest_prio(&mut self) -> Option<u32> { self.order.peek_highest_prio() }
+  fn activity(&self, var: u32) -> f32 { self.order.activity(var) }
+  fn set_activity(&mut self, var: u32, act: f32) { self.order.set_activity(var, act) }
+  fn clear(&mut self) {
+    self.order.clear();
+    self.assigned_at.clear();
+    self.participated.clear();
+    self.reasoned.clear();
+    self.conflicts = 0;
+    self.step_size = DEFAULT_STEP_SIZE;
+  }
+  fn resize(&mut self, vars: u32) {
+    self.order.resize(vars);
+    self.assigned_at.resize(vars as usize, 0);
+    self.participated.resize(vars as usize, 0);
+    self.reasoned.resize(vars as usize, 0);
+  }
+}
+
+impl Default for Lrb {
+  fn default() -> Self { Self::new() }
+}
+
+/// Marks the end of the VMTF queue.
+const NO_VAR: u32 = u32::MAX;
+
+/// Variable Move To Front (Ryan 2004), as used by CaDiCaL in focused mode.
+/// Keeps variables in a queue ordered by when they were last bumped, and picks the most recently
+/// bumped variable which has not been picked yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vmtf {
+  /// Less recently bumped neighbour of each variable
+  prev: Vec<u32>,
+  /// More recently bumped neighbour of each variable
+  next: Vec<u32>,
+  /// When each variable was last bumped
+  stamps: Vec<u64>,
+  /// Which variables cannot be picked until they are re-enabled
+  taken: Vec<bool>,
+  /// Least recently bumped variable
+  first: u32,
+  /// Most recently bumped variable
+  last: u32,
+  /// Every variable bumped more recently than this one has been taken
+  search: u32,
+  stamp: u64,
+}
+
+impl Vmtf {
+  pub fn new() -> Self {
+    Self {
+      prev: vec![],
+      next: vec![],
+      stamps: vec![],
+      taken: vec![],
+      first: NO_VAR,
+      last: NO_VAR,
+      search: NO_VAR,
+      stamp: 0,
+    }
+  }
+  fn unlink(&mut self, var: u32) {
+    let (prev, next) = (self.prev[var as usize], self.next[var as usize]);
+    if prev == NO_VAR {
+      self.first = next;
+    } else {
+      self.next[prev as usize] = next;
+    }
+    if next == NO_VAR {
+      self.last = prev;
+    } else {
+      self.prev[next as usize] = prev;
+    }
+  }
+  fn push_back(&mut self, var: u32) {
+    self.prev[var as usize] = self.last;
+    self.next[var as usize] = NO_VAR;
+    if self.last == NO_VAR {
+      self.first = var;
+    } else {
+      self.next[self.last as usize] = var;
+    }
+    self.last = var;
+    self.stamp += 1;
+    self.stamps[var as usize] = self.stamp;
+  }
+}
+
+impl DecisionHeuristic for Vmtf {
+  fn decay(&mut self) {}
+  fn increase_var_activity(&mut self, var: u32) {
+    if var == self.last {
+      return;
+    }
+    if var == self.search {
+      self.search = self.prev[var as usize];
+    }
+    self.unlink(var);
+    self.push_back(var);
+    if !self.taken[var as usize] || self.search == NO_VAR {
+      self.search = var;
+    }
+  }
+  fn enable(&mut self, var: u32) {
+    self.taken[var as usize] = false;
+    if self.search == NO_VAR || self.stamps[var as usize] > self.stamps[self.search as usize] {
+      self.search = var;
+    }
+  }
+  fn take_highest_prio(&mut self) -> Option<u32> {
+    let var = self.peek_highest_prio()?;
+    self.taken[var as usize] = true;
+    Some(var)
+  }
+  fn peek_highest_prio(&mut self) -> Option<u32> {
+    let mut var = self.search;
+    while var != NO_VAR && self.taken[var as usize] {
+      var = self.prev[var as usize];
+    }
+    if var == NO_VAR {
+      return None;
+    }
+    self.search = var;
+    Some(var)
+  }
+  fn activity(&self, var: u32) -> f32 { self.stamps[var as usize] as f32 }
+  /// VMTF only keeps the order in which scores are set, by moving the variable to the front.
+  fn set_activity(&mut self, var: u32, _act: f32) { self.increase_var_activity(var) }
+  fn clear(&mut self) { *self = Self::new(); }
+  fn resize(&mut self, vars: u32) {
+    for var in self.stamps.len() as u32..vars {
+      self.prev.push(NO_VAR);
+      self.next.push(NO_VAR);
+      self.stamps.push(0);
+      self.taken.push(false);
+      self.push_back(var);
+    }
+    self.search = self.last;
+  }
+}
+
+impl Default for Vmtf {
+  fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{test_util::check_solver, Options};
+
+  const HEURISTICS: [Heuristic; 4] = [
+    Heuristic::Vsids,
+    Heuristic::Chb,
+    Heuristic::Lrb,
+    Heuristic::Vmtf,
+  ];
+
+  #[test]
+  pub fn test_takes_highest_activity() {
+    let mut rng = Rng::new(1);
+    for &heuristic in &HEURISTICS {
+      for &seed in &[None, Some(3)] {
+        let vars = 20;
+        let mut h = heuristic.build(seed);
+        h.resize(vars);
+        let mut taken = vec![false; vars as usize];
+        for _ in 0..5000 {
+          let var = rng.below(vars);
+          match rng.below(4) {
+            0 => {
+              let best = (0..vars)
+                .filter(|&v| !taken[v as usize])
+                .map(|v| h.activity(v))
+                .fold(None, |best: Option<f32>, act| {
+                  Some(best.map_or(act, |b| b.max(act)))
+                });
+              let peeked = h.peek_highest_prio();
+              assert_eq!(peeked.map(|v| h.activity(v)), best, "{:?}", heuristic);
+              assert_eq!(h.take_highest_prio(), peeked, "{:?}", heuristic);
+              if let Some(v) = peeked {
+                assert!(!taken[v as usize], "{:?}", heuristic);
+                taken[v as usize] = true;
+                h.assigned(v);
+              }
+            },
+            1 if taken[var as usize] => {
+              taken[var as usize] = false;
+              h.enable(var);
+            },
+            2 => h.increase_var_activity(var),
+            _ => h.decay(),
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_chb_rewards_assignments() {
+    let mut chb = Chb::new();
+    chb.step_size_dec = 0.0;
+    chb.resize(3);
+    // the propagation assigning 0 leads to a conflict involving 1
+    chb.assigned(0);
+    chb.increase_var_activity(1);
+    chb.decay();
+    assert!((chb.activity(0) - 0.4).abs() < 1e-6);
+    // only assignments are rewarded, analysis just records the conflict
+    assert_eq!(chb.activity(1), 0.0);
+    assert_eq!(chb.last_conflict[1], 0);
+    // the propagation assigning 1 and 2 finishes without a conflict before the next decision
+    chb.assigned(1);
+    chb.assigned(2);
+    chb.take_highest_prio();
+    assert!((chb.activity(1) - 0.4 * 0.9 / 2.0).abs() < 1e-6);
+    assert!((chb.activity(2) - 0.4 * 0.9 / 2.0).abs() < 1e-6);
+  }
+
+  #[test]
+  pub fn test_solves_with_each_heuristic() {
+    for &heuristic in &HEURISTICS {
+      let mut options = Options::new();
+      options.heuristic = heuristic;
+      check_solver(&options, 1);
+    }
+  }
+}
@@ -2,19 +2,42 @@
 #![feature(slice_partition_at_index)]
 #![feature(div_duration)]
 
+pub mod approx;
+pub use approx::ApproxCounter;
+pub mod count;
+pub use count::{BigUint, Counter};
 pub mod database;
 pub use database::{CRef, Database};
+pub mod encode;
+mod heuristic;
+pub use heuristic::{Chb, DecisionHeuristic, Heuristic, Lrb};
 pub mod literal;
 pub use literal::Literal;
 mod luby;
-pub use luby::RestartState;
+pub use luby::{RestartPolicy, RestartState};
+pub mod maxsat;
+pub use maxsat::MaxSat;
+mod mode;
+pub use mode::{ModeState, SearchMode};
+mod options;
+pub use options::Options;
 pub mod parser;
+mod preprocess;
+pub use preprocess::{Preprocessor, Reconstruction};
+mod phase;
+pub use phase::{Phase, PhaseState};
+mod rng;
+pub use rng::Rng;
+mod sample;
+pub use sample::{Sampler, Samples};
 mod stats;
 pub use stats::Stats;
+#[cfg(test)]
+mod test_util;
 mod var_state;
 pub use var_state::VariableState;
 mod watch_list;
 pub use watch_list::WatchList;
 
 mod solver;
-pub use solver::Solver;
+pub use solver::{Models, Solver};
@@ -65,10 +65,10 @@ mod test {
   pub fn test_new_literal() {
     (1..42i32).for_each(|var| {
       let lit = Literal::from(-var);
-      assert_eq!(lit.var(), (var - 1) as usize);
+      assert_eq!(lit.var(), (var - 1) as u32);
       assert!(lit.negated());
       assert_eq!(lit.val(), false);
-      assert_eq!((!lit).var(), (var - 1) as usize);
+      assert_eq!((!lit).var(), (var - 1) as u32);
       assert!(!(!lit).negated());
       assert_eq!((!lit).val(), true);
     });
@@ -16,16 +16,69 @@ pub const fn luby(mut x: u64, y: u64) -> u64 {
   y.pow(seq)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// How a solver decides when to restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RestartPolicy {
+  /// Reluctant doubling, restarting after a Luby sequence of conflicts.
+  Luby,
+  /// Glucose style, restarting when recent learnt clauses have much worse LBD than average.
+  Ema,
+}
+
+/// Exponential moving average with bias correction, as in Kissat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ema {
+  value: f32,
+  biased: f32,
+  alpha: f32,
+  /// (1 - alpha)^updates, used to correct for the initial value of 0.
+  exp: f32,
+}
+
+impl Ema {
+  pub const fn new(alpha: f32) -> Self {
+    Self {
+      value: 0.0,
+      biased: 0.0,
+      alpha,
+      exp: 1.0,
+    }
+  }
+  pub fn update(&mut self, x: f32) {
+    self.biased += self.alpha * (x - self.biased);
+    self.exp *= 1.0 - self.alpha;
+    self.value = self.biased / (1.0 - self.exp);
+  }
+  pub const fn value(&self) -> f32 { self.value }
+}
+
+pub const EMA_FAST_ALPHA: f32 = 0.03;
+pub const EMA_SLOW_ALPHA: f32 = 1e-5;
+/// How much worse the recent LBD must be than the average to trigger a restart
+pub const EMA_RESTART_MARGIN: f32 = 1.1;
+/// Minimum conflicts between restarts using the EMA policy
+pub const EMA_RESTART_MIN: u64 = 50;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct RestartState {
   pub base_restart_interval: u64,
   pub restart_inc_interval: u64,
 
+  /// Which policy is used to suggest restarts
+  pub policy: RestartPolicy,
+
   /// Number of previous restarts
   num_restarts: u64,
 
   /// Number of conflicts remaining before restart
   remaining: u64,
+
+  /// Number of conflicts since the last restart
+  since_restart: u64,
+
+  /// Averages of LBD for learnt clauses over the short and long term
+  fast_lbd: Ema,
+  slow_lbd: Ema,
 }
 
 impl RestartState {
@@ -33,15 +86,87 @@ impl RestartState {
     Self {
       base_restart_interval: base,
       restart_inc_interval: inc,
+      policy: RestartPolicy::Luby,
       num_restarts: 0,
       remaining: base * luby(inc, 0),
+      since_restart: 0,
+      fast_lbd: Ema::new(EMA_FAST_ALPHA),
+      slow_lbd: Ema::new(EMA_SLOW_ALPHA),
+    }
+  }
+  pub fn mark_conflict(&mut self) {
+    self.remaining = self.remaining.saturating_sub(1);
+    self.since_restart += 1;
+  }
+  /// Records the literal block distance of a newly learnt clause
+  pub fn record_lbd(&mut self, lbd: u32) {
+    self.fast_lbd.update(lbd as f32);
+    self.slow_lbd.update(lbd as f32);
+  }
+  pub fn restart_suggested(&self) -> bool {
+    match self.policy {
+      RestartPolicy::Luby => self.remaining == 0,
+      RestartPolicy::Ema => {
+        self.since_restart >= EMA_RESTART_MIN
+          && self.fast_lbd.value() > EMA_RESTART_MARGIN * self.slow_lbd.value()
+      },
     }
   }
-  pub fn mark_conflict(&mut self) { self.remaining = self.remaining.saturating_sub(1); }
-  pub const fn restart_suggested(&self) -> bool { self.remaining == 0 }
   pub fn restart(&mut self) {
-    self.num_restarts += 1;
-    self.remaining =
-      luby(self.restart_inc_interval, self.num_restarts) * self.base_restart_interval;
+    self.since_restart = 0;
+    if self.policy == RestartPolicy::Luby {
+      self.num_restarts += 1;
+      self.remaining =
+        luby(self.restart_inc_interval, self.num_restarts) * self.base_restart_interval;
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Rng;
+
+  #[test]
+  pub fn test_ema_is_weighted_average() {
+    let mut rng = Rng::new(1);
+    for &alpha in &[EMA_FAST_ALPHA, 0.5, EMA_SLOW_ALPHA] {
+      let mut ema = Ema::new(alpha);
+      let mut xs = vec![];
+      for _ in 0..200 {
+        let x = rng.below(30) as f32;
+        ema.update(x);
+        xs.push(x as f64);
+        // each value is weighted by (1 - alpha)^age, normalized by the total weight
+        let weights = (0..xs.len()).map(|age| (1.0 - alpha as f64).powi(age as i32));
+        let total: f64 = weights.clone().sum();
+        let weighted: f64 = xs.iter().rev().zip(weights).map(|(x, w)| x * w).sum();
+        let expected = weighted / total;
+        // f32 loses precision in the bias correction for tiny alpha
+        let error = (ema.value() as f64 - expected).abs() / expected.max(1.0);
+        assert!(error < 1e-2, "{} {}", alpha, xs.len());
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_ema_restarts() {
+    let mut state = RestartState::new(100, 2);
+    state.policy = RestartPolicy::Ema;
+    for _ in 0..1000 {
+      state.record_lbd(5);
+      state.mark_conflict();
+    }
+    assert!(!state.restart_suggested());
+    state.restart();
+    // much worse clauses, but not enough conflicts since the restart
+    for i in 0..EMA_RESTART_MIN {
+      assert!(!state.restart_suggested(), "{}", i);
+      state.record_lbd(20);
+      state.mark_conflict();
+    }
+    assert!(state.restart_suggested());
+    state.restart();
+    assert!(!state.restart_suggested());
   }
 }
@@ -0,0 +1,371 @@
+use crate::{
+  encode::{GeneralizedTotalizer, Totalizer},
+  parser::from_wcnf,
+  Literal, Options, Solver,
+};
+use hashbrown::HashMap;
+use rustc_hash::FxHasher;
+use std::{hash::BuildHasherDefault, io, path::Path};
+
+/// Times each core is shrunk by solving again under only its own assumptions
+pub const TRIM_ROUNDS: u32 = 3;
+
+/// Weighted MaxSAT solver, which finds a solution of the hard clauses minimizing the total
+/// weight of falsified soft clauses, using assumptions and cores of an incremental `Solver`.
+#[derive(Debug)]
+pub struct MaxSat {
+  solver: Solver,
+  /// Soft clauses with their weights, which solutions are costed by
+  soft: Vec<(Vec<Literal>, u64)>,
+  /// Literals which are true when the soft clause at the same index is satisfied, for the soft
+  /// clauses which have been relaxed so far
+  soft_lits: Vec<Literal>,
+  /// Weight of empty soft clauses, which every solution falsifies
+  fixed_cost: u64,
+  /// Number of variables in the input, which solutions are given over
+  num_vars: u32,
+  /// Best solution found so far with its cost
+  best: Option<(u64, Vec<Option<bool>>)>,
+
+  /// Lower bound on the cost of any solution
+  pub lower_bound: u64,
+  /// Number of cores found while solving
+  pub cores: u64,
+}
+
+impl MaxSat {
+  pub fn new() -> Self { Self::with_options(Options::new()) }
+  pub fn with_options(options: Options) -> Self {
+    Self {
+      solver: Solver::with_options(options),
+      soft: vec![],
+      soft_lits: vec![],
+      fixed_cost: 0,
+      num_vars: 0,
+      best: None,
+      lower_bound: 0,
+      cores: 0,
+    }
+  }
+  /// Loads the hard and soft clauses of a WCNF file into this solver.
+  /// Returns false if the hard clauses have no solution.
+  pub fn load_wcnf<S: AsRef<Path>>(&mut self, s: S) -> io::Result<bool> {
+    let wcnf = from_wcnf(s)?;
+    self.resize(wcnf.num_vars);
+    let mut no_conflict = true;
+    for lits in &wcnf.hard {
+      no_conflict &= self.add_hard(lits);
+    }
+    for (lits, weight) in &wcnf.soft {
+      self.add_soft(lits, *weight);
+    }
+    Ok(no_conflict)
+  }
+  /// Adds a clause which every solution must satisfy.
+  /// Returns false if there is no solution anymore.
+  pub fn add_hard(&mut self, lits: &[Literal]) -> bool {
+    self.resize_to(lits);
+    self.solver.add_clause(lits)
+  }
+  /// Adds a clause which costs weight if a solution falsifies it.
+  pub fn add_soft(&mut self, lits: &[Literal], weight: u64) {
+    if weight == 0 {
+      return;
+    }
+    if lits.is_empty() {
+      self.fixed_cost += weight;
+      return;
+    }
+    self.resize_to(lits);
+    self.soft.push((lits.to_vec(), weight));
+  }
+  /// Returns the number of variables in the input.
+  pub fn num_vars(&self) -> u32 { self.num_vars }
+  /// Resizes this solver to hold at least max_vars input variables.
+  pub fn resize(&mut self, max_vars: u32) {
+    if max_vars > self.num_vars {
+      self.num_vars = max_vars;
+      self.solver.resize(max_vars);
+    }
+  }
+  fn resize_to(&mut self, lits: &[Literal]) {
+    let max_var = lits.iter().map(|lit| lit.var() + 1).max().unwrap_or(0);
+    self.resize(max_var);
+  }
+  /// Returns the total weight of soft clauses falsified by a model.
+  pub fn cost(&self, model: &[Option<bool>]) -> u64 {
+    let falsified = self
+      .soft
+      .iter()
+      .filter(|(lits, _)| lits.iter().all(|lit| lit.assn(model) != Some(true)))
+      .map(|&(_, weight)| weight)
+      .sum::<u64>();
+    self.fixed_cost + falsified
+  }
+  /// Returns the best solution found by the last solve over the input variables, with its cost.
+  pub fn best(&self) -> Option<(u64, &[Option<bool>])> {
+    self
+      .best
+      .as_ref()
+      .map(|(cost, model)| (*cost, model.as_slice()))
+  }
+
+  /// Finds a solution of least cost with OLL (Morgado et al. 2014) in the style of RC2
+  /// (Ignatiev et al. 2019), calling improved with the cost and model of each solution which is
+  /// better than every earlier one. Returns false if the hard clauses have no solution.
+  ///
+  /// Soft clauses are assumed to be satisfied until the assumptions fail. The weight of the
+  /// lightest clause in the core is added to the lower bound and taken from each clause in it,
+  /// and a totalizer over the core allows one more of them to be falsified, at the same weight.
+  /// Heavier clauses are assumed first, and lighter ones only once those are satisfied.
+  pub fn solve_oll(&mut self, mut improved: impl FnMut(u64, &[Option<bool>])) -> bool {
+    self.best = None;
+    self.cores = 0;
+    self.lower_bound = self.fixed_cost;
+    self.relax();
+    // weight left for each assumption, which are merged if soft clauses share a literal
+    let mut weights = HashMap::<_, _, BuildHasherDefault<FxHasher>>::default();
+    for (&lit, &(_, weight)) in self.soft_lits.iter().zip(self.soft.iter()) {
+      *weights.entry(lit).or_insert(0) += weight;
+    }
+    let mut totalizers = vec![];
+    // assumptions which bound the sum of a totalizer, with the bound they allow
+    let mut sums = HashMap::<_, (usize, usize), BuildHasherDefault<FxHasher>>::default();
+    let mut stratum = weights.values().copied().max().unwrap_or(0);
+    let mut assumptions = vec![];
+    loop {
+      assumptions.clear();
+      assumptions.extend(
+        weights
+          .iter()
+          .filter(|&(_, &weight)| weight >= stratum)
+          .map(|(&lit, _)| lit),
+      );
+      assumptions.sort_unstable();
+      if self.solver.solve_with_assumptions(&assumptions) {
+        self.record(&mut improved);
+        if self.best.as_ref().map(|&(cost, _)| cost) == Some(self.lower_bound) {
+          return true;
+        }
+        match weights.values().copied().filter(|&w| w < stratum).max() {
+          Some(weight) => stratum = weight,
+          // every soft clause was satisfied or relaxed, so the solution is optimal
+          None => return true,
+        }
+        continue;
+      }
+      let mut core = self.solver.failed_assumptions().to_vec();
+      if core.is_empty() {
+        return false;
+      }
+      self.trim(&mut core);
+      self.cores += 1;
+      let weight = core.iter().map(|lit| weights[lit]).min().unwrap();
+      self.lower_bound += weight;
+      for lit in &core {
+        let left = weights[lit] - weight;
+        if left == 0 {
+          weights.remove(lit);
+        } else {
+          weights.insert(*lit, left);
+        }
+        // a sum in the core allows one more of its inputs, unless it already allows all of them
+        if let Some(&(t, bound)) = sums.get(lit) {
+          let totalizer: &mut Totalizer = &mut totalizers[t];
+          if let Some(out) = totalizer.at_least(&mut self.solver, bound + 2) {
+            *weights.entry(!out).or_insert(0) += weight;
+            sums.insert(!out, (t, bound + 1));
+          }
+        }
+      }
+      if let [lit] = core[..] {
+        self.solver.add_clause(&[!lit]);
+        continue;
+      }
+      // one of the core is falsified, so its sum is bounded to 1 first
+      let inputs = core.iter().map(|&lit| !lit).collect::<Vec<_>>();
+      let mut totalizer = Totalizer::new(&inputs);
+      if let Some(out) = totalizer.at_least(&mut self.solver, 2) {
+        *weights.entry(!out).or_insert(0) += weight;
+        sums.insert(!out, (totalizers.len(), 1));
+      }
+      totalizers.push(totalizer);
+    }
+  }
+
+  /// Finds a solution of least cost with linear SAT-UNSAT search, calling improved as in
+  /// `solve_oll`. Returns false if the hard clauses have no solution.
+  ///
+  /// Once any solution is found, a generalized totalizer sums the weights of the falsified soft
+  /// clauses, and its outputs at or above the cost of the best solution are assumed false until
+  /// there is no better solution. Good solutions are found early, which helps where cores are
+  /// too large or too many for core-guided search.
+  pub fn solve_lsu(&mut self, mut improved: impl FnMut(u64, &[Option<bool>])) -> bool {
+    self.best = None;
+    self.cores = 0;
+    self.lower_bound = self.fixed_cost;
+    self.relax();
+    if !self.solver.solve() {
+      return false;
+    }
+    self.record(&mut improved);
+    let mut bound = self.best.as_ref().unwrap().0 - self.fixed_cost;
+    // every soft clause is satisfied
+    if bound == 0 {
+      return true;
+    }
+    let inputs = self
+      .soft_lits
+      .iter()
+      .zip(self.soft.iter())
+      .map(|(&lit, &(_, weight))| (!lit, weight))
+      .collect::<Vec<_>>();
+    // sums past the first bound are never allowed, so they are all given by one output
+    let totalizer = GeneralizedTotalizer::new(&mut self.solver, &inputs, bound);
+    let mut assumptions = vec![];
+    while bound > 0 {
+      assumptions.clear();
+      assumptions.extend(
+        totalizer
+          .outputs()
+          .iter()
+          .filter(|&&(sum, _)| sum >= bound)
+          .map(|&(_, lit)| !lit),
+      );
+      if !self.solver.solve_with_assumptions(&assumptions) {
+        break;
+      }
+      self.record(&mut improved);
+      bound = self.best.as_ref().unwrap().0 - self.fixed_cost;
+    }
+    self.lower_bound = self.best.as_ref().unwrap().0;
+    true
+  }
+
+  /// Adds a literal for each soft clause which is not relaxed yet, which is the clause itself
+  /// if it is a unit, or otherwise the negation of a new variable added to it.
+  fn relax(&mut self) {
+    for i in self.soft_lits.len()..self.soft.len() {
+      let lit = match self.soft[i].0[..] {
+        [lit] => lit,
+        _ => {
+          let relax = Literal::new(self.solver.new_var(), false);
+          let mut clause = self.soft[i].0.clone();
+          clause.push(relax);
+          self.solver.add_clause(&clause);
+          !relax
+        },
+      };
+      // soft literals are assumed in later rounds, so they must survive preprocessing
+      self.solver.set_frozen(lit.var(), true);
+      self.soft_lits.push(lit);
+    }
+  }
+  /// Shrinks a core by solving again under only its own assumptions.
+  fn trim(&mut self, core: &mut Vec<Literal>) {
+    for _ in 0..TRIM_ROUNDS {
+      if core.len() <= 1 {
+        return;
+      }
+      let sat = self.solver.solve_with_assumptions(core);
+      debug_assert!(!sat, "A core was satisfiable");
+      let smaller = self.solver.failed_assumptions();
+      if sat || smaller.is_empty() || smaller.len() >= core.len() {
+        return;
+      }
+      core.clear();
+      core.extend_from_slice(smaller);
+    }
+  }
+  /// Keeps the solver's model if it is better than the best so far.
+  fn record(&mut self, improved: &mut impl FnMut(u64, &[Option<bool>])) {
+    let model = self.solver.final_assignments();
+    let cost = self.cost(model);
+    let better = match &self.best {
+      None => true,
+      Some((best, _)) => cost < *best,
+    };
+    if better {
+      let model = model[..self.num_vars as usize].to_vec();
+      improved(cost, &model);
+      self.best = Some((cost, model));
+    }
+  }
+}
+
+impl Default for MaxSat {
+  fn default() -> Self { Self::new() }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    test_util::{assignments, random_clauses, satisfies},
+    Rng,
+  };
+
+  /// Solves random weighted formulas, checking the cost of the best solution against the least
+  /// cost of any assignment satisfying the hard clauses.
+  fn check_maxsat(
+    seed: u64,
+    solve: impl Fn(&mut MaxSat, &mut dyn FnMut(u64, &[Option<bool>])) -> bool,
+  ) {
+    let mut rng = Rng::new(seed);
+    for i in 0..200 {
+      let vars = 1 + i % 8;
+      let num_hard = rng.below(2 * vars) as usize;
+      let hard = random_clauses(&mut rng, vars, num_hard, 3);
+      let num_soft = 1 + rng.below(3 * vars) as usize;
+      let mut soft = random_clauses(&mut rng, vars, num_soft, 2)
+        .into_iter()
+        .map(|clause| (clause, if i % 2 == 0 { 1 } else { rng.below(6) as u64 }))
+        .collect::<Vec<_>>();
+      if i % 5 == 0 {
+        soft.push((vec![], 3));
+      }
+      let cost = |model: &[Option<bool>]| -> u64 {
+        soft
+          .iter()
+          .filter(|(clause, _)| clause.iter().all(|lit| lit.assn(model) != Some(true)))
+          .map(|&(_, weight)| weight)
+          .sum()
+      };
+      let optimum = assignments(vars)
+        .filter(|model| satisfies(model, &hard))
+        .map(|model| cost(&model))
+        .min();
+
+      let mut maxsat = MaxSat::new();
+      maxsat.resize(vars);
+      for clause in &hard {
+        maxsat.add_hard(clause);
+      }
+      for (clause, weight) in &soft {
+        maxsat.add_soft(clause, *weight);
+      }
+      let mut costs = vec![];
+      let sat = solve(&mut maxsat, &mut |cost, model| {
+        assert!(satisfies(model, &hard));
+        costs.push(cost);
+      });
+      assert_eq!(sat, optimum.is_some(), "{:?} {:?}", hard, soft);
+      let optimum = match optimum {
+        None => continue,
+        Some(optimum) => optimum,
+      };
+      let (best, model) = maxsat.best().unwrap();
+      assert_eq!(best, optimum, "{:?} {:?}", hard, soft);
+      assert_eq!(cost(model), optimum);
+      assert_eq!(maxsat.lower_bound, optimum);
+      // each solution reported is better than the last
+      assert!(costs.windows(2).all(|w| w[0] > w[1]), "{:?}", costs);
+      assert_eq!(costs.last(), Some(&optimum));
+    }
+  }
+
+  #[test]
+  pub fn test_oll() { check_maxsat(1, |maxsat, improved| maxsat.solve_oll(improved)); }
+  #[test]
+  pub fn test_lsu() { check_maxsat(2, |maxsat, improved| maxsat.solve_lsu(improved)); }
+}
@@ -0,0 +1,117 @@
+use crate::Options;
+
+/// Which kind of search a solver is currently performing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SearchMode {
+  /// Frequent restarts with VMTF, which tends to be better at finding conflicts.
+  Focused,
+  /// Infrequent restarts with the configured heuristic, which tends to be better at finding
+  /// solutions.
+  Stable,
+}
+
+/// Tracks when a solver should switch between focused and stable search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModeState {
+  pub mode: SearchMode,
+
+  /// Conflicts allowed in the current phase
+  conflict_budget: u64,
+  /// Ticks allowed in the current phase
+  tick_budget: Option<u64>,
+
+  /// Conflicts and ticks when the current phase started
+  conflicts_at_start: u64,
+  ticks_at_start: u64,
+
+  /// How much each budget grows after each phase
+  inc: f32,
+}
+
+impl ModeState {
+  pub fn new(options: &Options) -> Self {
+    Self {
+      mode: SearchMode::Focused,
+      conflict_budget: options.mode_conflicts,
+      tick_budget: options.mode_ticks,
+      conflicts_at_start: 0,
+      ticks_at_start: 0,
+      inc: options.mode_inc,
+    }
+  }
+  /// Has the current phase used up its budget?
+  pub fn switch_suggested(&self, conflicts: u64, ticks: u64) -> bool {
+    let ticks_used = ticks - self.ticks_at_start;
+    conflicts - self.conflicts_at_start >= self.conflict_budget
+      || matches!(self.tick_budget, Some(budget) if ticks_used >= budget)
+  }
+  /// Starts the next phase, returning which mode it is in.
+  pub fn switch(&mut self, conflicts: u64, ticks: u64) -> SearchMode {
+    self.mode = match self.mode {
+      SearchMode::Focused => SearchMode::Stable,
+      SearchMode::Stable => SearchMode::Focused,
+    };
+    self.conflicts_at_start = conflicts;
+    self.ticks_at_start = ticks;
+    self.conflict_budget = (self.conflict_budget as f32 * self.inc) as u64;
+    let inc = self.inc;
+    if let Some(budget) = self.tick_budget.as_mut() {
+      *budget = (*budget as f32 * inc) as u64;
+    }
+    self.mode
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_util::check_solver;
+
+  #[test]
+  pub fn test_budgets_grow() {
+    let mut options = Options::new();
+    options.mode_conflicts = 10;
+    options.mode_ticks = Some(1000);
+    options.mode_inc = 1.5;
+    let mut state = ModeState::new(&options);
+    let (mut conflicts, mut ticks) = (0, 0);
+    let (mut conflict_budget, mut tick_budget) = (10, 1000);
+    let mut expected = SearchMode::Focused;
+    for i in 0..20 {
+      assert_eq!(state.mode, expected);
+      // alternate which budget runs out first
+      let (c, t) = if i % 2 == 0 {
+        (conflict_budget, tick_budget - 1)
+      } else {
+        (conflict_budget - 1, tick_budget)
+      };
+      assert!(!state.switch_suggested(conflicts + c - 1, ticks + t - 1));
+      assert!(state.switch_suggested(conflicts + c, ticks + t));
+      conflicts += c;
+      ticks += t;
+      expected = match expected {
+        SearchMode::Focused => SearchMode::Stable,
+        SearchMode::Stable => SearchMode::Focused,
+      };
+      assert_eq!(state.switch(conflicts, ticks), expected);
+      conflict_budget = (conflict_budget as f32 * 1.5) as u64;
+      tick_budget = (tick_budget as f32 * 1.5) as u64;
+    }
+  }
+
+  #[test]
+  pub fn test_solves_while_switching() {
+    let mut options = Options::new();
+    options.mode_switching = true;
+    for &conflicts in &[1, 5, 50] {
+      options.mode_conflicts = conflicts;
+      check_solver(&options, conflicts);
+    }
+    // phases which run out of ticks before conflicts
+    options.mode_conflicts = 1000;
+    for &ticks in &[10, 1000] {
+      options.mode_ticks = Some(ticks);
+      check_solver(&options, ticks);
+    }
+  }
+}
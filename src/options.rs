@@ -0,0 +1,110 @@
+use crate::{rng::DEFAULT_SEED, Heuristic, Phase};
+
+/// Tunable parameters for a solver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+  /// Which heuristic to use when picking decision variables.
+  /// Changes take effect when the solver is next cleared.
+  pub heuristic: Heuristic,
+
+  /// Whether to alternate between focused and stable search.
+  /// Focused search uses VMTF with frequent restarts, and stable search uses `heuristic` with
+  /// reluctant doubling restarts.
+  pub mode_switching: bool,
+  /// Conflicts allowed in the first search phase.
+  pub mode_conflicts: u64,
+  /// Ticks allowed in the first search phase, if limited, counted as watched clauses visited
+  /// while propagating.
+  pub mode_ticks: Option<u64>,
+  /// How much each phase's budget grows from the previous phase.
+  pub mode_inc: f32,
+
+  /// Which value to assign decision variables.
+  pub phase: Phase,
+  /// Whether to periodically reset saved phases.
+  pub rephase: bool,
+  /// Conflicts before the first rephase, later rephases are spaced arithmetically.
+  pub rephase_interval: u64,
+
+  /// Seed for all randomness in the solver, the same seed always reproduces the same run.
+  /// A seed of 0 is the same as the default seed, since the generator cannot start from 0.
+  pub seed: u64,
+  /// Probability of picking a random decision variable instead of the heuristic's choice.
+  pub random_decision_freq: f32,
+  /// Whether to start variables with small random scores, breaking ties randomly.
+  pub random_init_activity: bool,
+
+  /// If set, backtrack chronologically when a learnt clause would jump back more than this
+  /// many levels.
+  pub chrono_threshold: Option<u32>,
+
+  /// Whether restarts keep decision levels which would be picked again.
+  pub reuse_trail: bool,
+
+  /// Whether to replace blocks of literals from the same level in learnt clauses with a single
+  /// implying literal.
+  pub shrink: bool,
+  /// Whether to remove literals from learnt clauses using binary clauses with the asserting
+  /// literal.
+  pub minimize_binary: bool,
+
+  /// Whether to eliminate variables by resolution before search.
+  pub elim: bool,
+  /// Whether to remove subsumed clauses and strengthen clauses by self-subsuming resolution,
+  /// before search and periodically at level 0.
+  pub subsume: bool,
+  /// Whether to probe each variable before search, forcing failed literals and literals implied
+  /// by both values of a variable.
+  pub probe: bool,
+  /// Whether probing adds binary clauses from each probed literal to the literals it implies
+  /// through longer clauses.
+  pub hyper_binary: bool,
+  /// Whether to replace equivalent literals found from binary clauses with a single literal.
+  pub substitute: bool,
+  /// Whether to remove blocked clauses before search.
+  pub block: bool,
+
+  /// Whether to periodically shorten clauses at restarts by propagating their negation.
+  pub vivify: bool,
+  /// Ticks allowed each time clauses are vivified, as a fraction of the ticks spent searching
+  /// since they were last vivified.
+  pub vivify_effort: f32,
+}
+
+impl Options {
+  pub const fn new() -> Self {
+    Self {
+      heuristic: Heuristic::Vsids,
+      mode_switching: false,
+      mode_conflicts: 1000,
+      mode_ticks: None,
+      mode_inc: 2.0,
+      phase: Phase::Saved,
+      rephase: false,
+      rephase_interval: 1000,
+      seed: DEFAULT_SEED,
+      random_decision_freq: 0.0,
+      random_init_activity: false,
+      chrono_threshold: None,
+      reuse_trail: false,
+      shrink: false,
+      minimize_binary: false,
+      elim: false,
+      subsume: false,
+      probe: false,
+      hyper_binary: false,
+      substitute: false,
+      block: false,
+      vivify: false,
+      vivify_effort: 0.1,
+    }
+  }
+  /// Whether any preprocessing technique is enabled.
+  pub const fn preprocessing(&self) -> bool {
+    self.elim || self.subsume || self.probe || self.substitute || self.block
+  }
+}
+
+impl Default for Options {
+  fn default() -> Self { Self::new() }
+}
@@ -57,3 +57,256 @@ pub fn from_dimacs_alloc<S: AsRef<Path>>(s: S, db: &mut Database) -> io::Result<
   from_dimacs(s, db, &mut out)?;
   Ok(out)
 }
+
+/// Reads the sampling set of a DIMACs file, given by comment lines starting with "c ind" and
+/// ending with 0, or None if there are no such lines.
+pub fn sampling_set<S: AsRef<Path>>(s: S) -> io::Result<Option<Vec<u32>>> {
+  let file = File::open(s)?;
+  let mut vars = None;
+  for l in BufReader::new(file).lines() {
+    let l = l?;
+    let l = l.trim();
+    if !l.starts_with("c ind ") {
+      continue;
+    }
+    let vars = vars.get_or_insert_with(Vec::new);
+    for v in l[6..].split_whitespace() {
+      let v = v.parse::<u32>().expect("Failed to parse sampling variable");
+      if v != 0 {
+        vars.push(v - 1);
+      }
+    }
+  }
+  Ok(vars)
+}
+
+/// Returns whether a file has a "p inccnf" header, and should be read with `from_icnf`.
+pub fn is_icnf<S: AsRef<Path>>(s: S) -> io::Result<bool> {
+  let file = File::open(s)?;
+  for l in BufReader::new(file).lines() {
+    let l = l?;
+    let l = l.trim();
+    if l.is_empty() || l.starts_with('c') {
+      continue;
+    }
+    return Ok(l.starts_with("p inccnf"));
+  }
+  Ok(false)
+}
+
+/// A line of an incremental CNF file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IcnfItem {
+  /// A clause which is kept for every later query.
+  Clause(Vec<Literal>),
+  /// A query for a solution where these literals are true.
+  Assumptions(Vec<Literal>),
+}
+
+/// Reads an incremental CNF file, with a "p inccnf" header, clauses as in DIMACs, and queries
+/// as lines starting with "a" and ending with 0.
+/// Returns the number of variables used and each clause and query in order.
+pub fn from_icnf<S: AsRef<Path>>(s: S) -> io::Result<(u32, Vec<IcnfItem>)> {
+  let file = File::open(s)?;
+  let buf_reader = BufReader::new(file);
+  let mut items = vec![];
+  let mut buf = vec![];
+  let mut assumptions = false;
+  let mut max_seen_var = 0;
+  for l in buf_reader.lines() {
+    let l = l?;
+    let mut l = l.trim();
+    if l.starts_with('c') || l.starts_with('p') {
+      continue;
+    } else if l.starts_with('a') {
+      assert!(buf.is_empty(), "Unterminated clause before assumptions");
+      assumptions = true;
+      l = &l[1..];
+    }
+    for v in l.split_whitespace() {
+      let v = v.parse::<i32>().expect("Failed to parse literal");
+      if v == 0 {
+        let lits = std::mem::take(&mut buf);
+        items.push(if assumptions {
+          IcnfItem::Assumptions(lits)
+        } else {
+          assert!(!lits.is_empty(), "Empty clause in input");
+          IcnfItem::Clause(lits)
+        });
+        assumptions = false;
+      } else {
+        let l = Literal::from(v);
+        assert!(
+          l.is_valid(),
+          "Too many variables to handle CNF file properly"
+        );
+        max_seen_var = max_seen_var.max(l.var() + 1);
+        buf.push(l);
+      }
+    }
+  }
+  Ok((max_seen_var, items))
+}
+
+/// A weighted CNF formula, whose solutions satisfy every hard clause, and cost the total weight
+/// of the soft clauses they falsify.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Wcnf {
+  pub num_vars: u32,
+  pub hard: Vec<Vec<Literal>>,
+  /// Soft clauses with their weights
+  pub soft: Vec<(Vec<Literal>, u64)>,
+}
+
+/// Reads a weighted CNF file, either with a "p wcnf" header where each clause starts with its
+/// weight and clauses weighing at least the optional top weight are hard, or in the 2022 format
+/// without a header, where hard clauses start with "h" instead of a weight.
+pub fn from_wcnf<S: AsRef<Path>>(s: S) -> io::Result<Wcnf> {
+  let file = File::open(s)?;
+  let buf_reader = BufReader::new(file);
+  let mut wcnf = Wcnf::default();
+  let mut top = u64::MAX;
+  let mut buf = vec![];
+  // weight of the current clause, which is None for hard clauses, once it has been read
+  let mut weight = None;
+  for l in buf_reader.lines() {
+    let l = l?;
+    let l = l.trim();
+    if l.is_empty() || l.starts_with('c') {
+      continue;
+    } else if l.starts_with("p wcnf") {
+      let mut items = l
+        .split_whitespace()
+        .skip(2)
+        .map(|v| v.parse::<u64>().expect("Failed to parse \"p wcnf\" header"));
+      let num_vars = items.next().expect("Missing # variables from \"p wcnf\"");
+      wcnf.num_vars = wcnf.num_vars.max(num_vars as u32);
+      top = items.nth(1).unwrap_or(u64::MAX);
+      continue;
+    }
+    for v in l.split_whitespace() {
+      let w = match weight {
+        None => {
+          weight = Some(match v {
+            "h" => None,
+            w => Some(w.parse::<u64>().expect("Failed to parse weight")).filter(|&w| w < top),
+          });
+          continue;
+        },
+        Some(w) => w,
+      };
+      let v = v.parse::<i32>().expect("Failed to parse literal");
+      if v == 0 {
+        let lits = std::mem::take(&mut buf);
+        match w {
+          None => wcnf.hard.push(lits),
+          Some(w) => wcnf.soft.push((lits, w)),
+        }
+        weight = None;
+      } else {
+        let l = Literal::from(v);
+        assert!(
+          l.is_valid(),
+          "Too many variables to handle WCNF file properly"
+        );
+        wcnf.num_vars = wcnf.num_vars.max(l.var() + 1);
+        buf.push(l);
+      }
+    }
+  }
+  Ok(wcnf)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    test_util::{random_clauses, temp_file},
+    Rng,
+  };
+  use std::fs;
+
+  /// Writes literals followed by 0, sometimes splitting them across lines.
+  fn write_lits(out: &mut String, lits: &[Literal], rng: &mut Rng) {
+    for lit in lits {
+      out.push_str(&lit.to_string());
+      out.push_str(if rng.below(4) == 0 { "\n" } else { "  " });
+    }
+    out.push_str("0\n");
+  }
+
+  #[test]
+  pub fn test_icnf() {
+    let mut rng = Rng::new(1);
+    for i in 0..50 {
+      let vars = 1 + rng.below(20);
+      let mut items = vec![];
+      let mut out = String::from("c comment\np inccnf\n");
+      for clause in random_clauses(&mut rng, vars, 30, 4) {
+        if rng.below(4) == 0 {
+          let assumptions = clause[..rng.below(clause.len() as u32) as usize].to_vec();
+          out.push_str("a ");
+          write_lits(&mut out, &assumptions, &mut rng);
+          items.push(IcnfItem::Assumptions(assumptions));
+        }
+        write_lits(&mut out, &clause, &mut rng);
+        items.push(IcnfItem::Clause(clause));
+      }
+      let path = temp_file(&format!("{}.icnf", i), &out);
+      assert!(is_icnf(&path).unwrap());
+      let (num_vars, parsed) = from_icnf(&path).unwrap();
+      fs::remove_file(path).unwrap();
+      let max_var = items
+        .iter()
+        .flat_map(|item| match item {
+          IcnfItem::Clause(lits) | IcnfItem::Assumptions(lits) => lits.iter(),
+        })
+        .map(|lit| lit.var() + 1)
+        .max();
+      assert_eq!(num_vars, max_var.unwrap());
+      assert_eq!(parsed, items, "{}", out);
+    }
+  }
+  #[test]
+  pub fn test_wcnf() {
+    let mut rng = Rng::new(2);
+    for i in 0..50 {
+      let vars = 1 + rng.below(20);
+      // the 2022 format has no header, and marks hard clauses with "h"
+      let old = i % 2 == 0;
+      let top = 1000;
+      let clauses = random_clauses(&mut rng, vars, 30, 4);
+      let mut expected = Wcnf::default();
+      let mut out = if old {
+        format!("c comment\np wcnf {} {} {}\n", vars, clauses.len(), top)
+      } else {
+        String::from("c comment\n")
+      };
+      for clause in clauses {
+        if rng.below(3) == 0 {
+          let weight = if old { top.to_string() } else { String::from("h") };
+          out.push_str(&format!("{} ", weight));
+          write_lits(&mut out, &clause, &mut rng);
+          expected.hard.push(clause);
+        } else {
+          let weight = 1 + rng.below(top - 1) as u64;
+          out.push_str(&format!("{} ", weight));
+          write_lits(&mut out, &clause, &mut rng);
+          expected.soft.push((clause, weight));
+        }
+      }
+      let max_var = expected
+        .hard
+        .iter()
+        .chain(expected.soft.iter().map(|(clause, _)| clause))
+        .flat_map(|clause| clause.iter().map(|lit| lit.var() + 1))
+        .max()
+        .unwrap();
+      expected.num_vars = if old { vars } else { max_var };
+      let path = temp_file(&format!("{}.wcnf", i), &out);
+      let parsed = from_wcnf(&path).unwrap();
+      fs::remove_file(path).unwrap();
+      assert_eq!(parsed, expected, "{}", out);
+    }
+  }
+}
@@ -0,0 +1,253 @@
+use crate::{Literal, Rng};
+
+/// Which value a decision variable should be assigned to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+  /// Always assign false
+  False,
+  /// Always assign true
+  True,
+  /// Assign a random value
+  Random,
+  /// Assign the last value the variable had, initially false
+  Saved,
+  /// Assign the value in the longest conflict-free trail since the last rephase
+  Target,
+  /// Assign the value in the longest conflict-free trail ever seen
+  Best,
+}
+
+/// What saved phases are reset to when rephasing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Rephase {
+  Original,
+  Inverted,
+  Best,
+  Random,
+  Flipped,
+}
+
+/// Order in which rephasing cycles through resets, always returning to the best phases.
+const REPHASE_SCHEDULE: [Rephase; 8] = [
+  Rephase::Original,
+  Rephase::Best,
+  Rephase::Inverted,
+  Rephase::Best,
+  Rephase::Random,
+  Rephase::Best,
+  Rephase::Flipped,
+  Rephase::Best,
+];
+
+/// Tracks target and best phases, and when the solver should next rephase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseState {
+  /// Phases supplied by the user, which override any policy
+  forced: Vec<Option<bool>>,
+  target: Vec<Option<bool>>,
+  target_len: usize,
+  best: Vec<Option<bool>>,
+  best_len: usize,
+
+  rephases: u64,
+  /// Conflicts between rephases, which grows arithmetically
+  interval: u64,
+  next_rephase: u64,
+}
+
+impl PhaseState {
+  pub const fn new(interval: u64) -> Self {
+    Self {
+      forced: vec![],
+      target: vec![],
+      target_len: 0,
+      best: vec![],
+      best_len: 0,
+      rephases: 0,
+      interval,
+      next_rephase: interval,
+    }
+  }
+  /// Records a conflict-free trail, updating target and best phases if it is the longest seen.
+  pub fn update(&mut self, trail: &[Literal]) {
+    if trail.len() > self.target_len {
+      self.target_len = trail.len();
+      for lit in trail {
+        self.target[lit.var() as usize] = Some(lit.val());
+      }
+    }
+    if trail.len() > self.best_len {
+      self.best_len = trail.len();
+      for lit in trail {
+        self.best[lit.var() as usize] = Some(lit.val());
+      }
+    }
+  }
+  /// Returns the phase to assign to var under this policy.
+  pub fn phase(&self, policy: Phase, var: u32, saved: &[bool], rng: &mut Rng) -> bool {
+    let var = var as usize;
+    if let Some(phase) = self.forced[var] {
+      return phase;
+    }
+    match policy {
+      Phase::False => false,
+      Phase::True => true,
+      Phase::Random => rng.next_bool(),
+      Phase::Saved => saved[var],
+      Phase::Target => self.target[var].unwrap_or(saved[var]),
+      Phase::Best => self.best[var].unwrap_or(saved[var]),
+    }
+  }
+  /// Forces var to always be assigned phase when it is a decision, or removes the hint if None.
+  pub fn force(&mut self, var: u32, phase: Option<bool>) { self.forced[var as usize] = phase; }
+  pub const fn rephase_suggested(&self, conflicts: u64) -> bool { conflicts >= self.next_rephase }
+  /// Resets saved phases following the rephasing schedule.
+  pub fn rephase(&mut self, conflicts: u64, saved: &mut [bool], rng: &mut Rng) {
+    let kind = REPHASE_SCHEDULE[(self.rephases % REPHASE_SCHEDULE.len() as u64) as usize];
+    self.rephases += 1;
+    self.next_rephase = conflicts + self.interval * (self.rephases + 1);
+    for (var, phase) in saved.iter_mut().enumerate() {
+      *phase = match kind {
+        Rephase::Original => false,
+        Rephase::Inverted => true,
+        Rephase::Best => self.best[var].unwrap_or(*phase),
+        Rephase::Random => rng.next_bool(),
+        Rephase::Flipped => !*phase,
+      };
+    }
+    if kind == Rephase::Best {
+      self.best_len = 0;
+    }
+    self.target_len = 0;
+    for t in self.target.iter_mut() {
+      *t = None;
+    }
+  }
+  pub fn resize(&mut self, vars: u32) {
+    self.forced.resize(vars as usize, None);
+    self.target.resize(vars as usize, None);
+    self.best.resize(vars as usize, None);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{test_util::check_solver, Options};
+
+  /// Returns a trail assigning a random prefix of the variables in a random order.
+  fn random_trail(rng: &mut Rng, vars: u32) -> Vec<Literal> {
+    let mut order = (0..vars).collect::<Vec<_>>();
+    for i in (1..order.len()).rev() {
+      order.swap(i, rng.below(i as u32 + 1) as usize);
+    }
+    let len = rng.below(vars + 1) as usize;
+    order[..len]
+      .iter()
+      .map(|&var| Literal::new(var, rng.next_bool()))
+      .collect()
+  }
+
+  #[test]
+  pub fn test_longest_trails() {
+    let mut rng = Rng::new(1);
+    let vars = 10;
+    let mut state = PhaseState::new(1000);
+    state.resize(vars);
+    let saved = vec![false; vars as usize];
+    let (mut target, mut best): (Vec<Literal>, Vec<Literal>) = (vec![], vec![]);
+    for i in 0..200 {
+      let trail = random_trail(&mut rng, vars);
+      state.update(&trail);
+      // ties keep the earlier trail
+      if trail.len() > target.len() {
+        target = trail.clone();
+      }
+      if trail.len() > best.len() {
+        best = trail;
+      }
+      for lit in &target {
+        assert_eq!(
+          state.phase(Phase::Target, lit.var(), &saved, &mut rng),
+          lit.val()
+        );
+      }
+      for lit in &best {
+        assert_eq!(
+          state.phase(Phase::Best, lit.var(), &saved, &mut rng),
+          lit.val()
+        );
+      }
+      if i % 50 == 49 {
+        state.rephase(i, &mut vec![false; vars as usize], &mut rng);
+        target.clear();
+        // every other rephase resets to the best phases, and then forgets them
+        if i % 100 == 99 {
+          best.clear();
+        }
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_rephase_schedule() {
+    let mut rng = Rng::new(2);
+    let vars = 10;
+    let mut state = PhaseState::new(10);
+    state.resize(vars);
+    let best = random_trail(&mut rng, vars);
+    state.update(&best);
+    let mut saved = (0..vars).map(|_| rng.next_bool()).collect::<Vec<_>>();
+    let mut conflicts = 0;
+    for i in 0..16 {
+      assert!(!state.rephase_suggested(conflicts));
+      // the interval grows by 10 after each rephase
+      conflicts += 10 * (i + 1) as u64;
+      assert!(state.rephase_suggested(conflicts));
+      let before = saved.clone();
+      state.rephase(conflicts, &mut saved, &mut rng);
+      for (var, (&phase, &before)) in saved.iter().zip(&before).enumerate() {
+        let best = best.iter().find(|lit| lit.var() == var as u32);
+        match REPHASE_SCHEDULE[i % REPHASE_SCHEDULE.len()] {
+          Rephase::Original => assert!(!phase),
+          Rephase::Inverted => assert!(phase),
+          Rephase::Best => assert_eq!(phase, best.map_or(before, |lit| lit.val())),
+          Rephase::Flipped => assert_eq!(phase, !before),
+          Rephase::Random => {},
+        }
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_forced_phases() {
+    let mut rng = Rng::new(3);
+    let mut state = PhaseState::new(10);
+    state.resize(2);
+    state.force(1, Some(true));
+    for &policy in &[Phase::False, Phase::Saved, Phase::Random, Phase::Best] {
+      assert!(state.phase(policy, 1, &[false, false], &mut rng));
+    }
+    state.force(1, None);
+    assert!(!state.phase(Phase::Saved, 1, &[false, false], &mut rng));
+  }
+
+  #[test]
+  pub fn test_solves_with_each_phase() {
+    let phases = [
+      Phase::False,
+      Phase::True,
+      Phase::Random,
+      Phase::Saved,
+      Phase::Target,
+      Phase::Best,
+    ];
+    for &phase in &phases {
+      let mut options = Options::new();
+      options.phase = phase;
+      options.rephase = true;
+      options.rephase_interval = 2;
+      check_solver(&options, 1);
+    }
+  }
+}
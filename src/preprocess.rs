@@ -0,0 +1,767 @@
+use crate::Literal;
+use std::{cmp::Reverse, mem::take};
+
+/// Variables occuring more than this many times in either polarity are not eliminated
+pub const ELIM_OCC_LIMIT: usize = 16;
+/// Variables whose elimination would create a resolvent longer than this are not eliminated
+pub const ELIM_CLAUSE_LIMIT: usize = 20;
+/// Clauses longer than this are not used to subsume other clauses
+pub const SUBSUME_CLAUSE_LIMIT: usize = 100;
+/// Literals whose negation occurs more than this many times are not used to block clauses
+pub const BLOCK_OCC_LIMIT: usize = 64;
+
+/// Marks a literal which has not been visited while finding strongly connected components
+const UNVISITED: u32 = u32::MAX;
+
+/// Clauses removed while preprocessing, which are needed to extend a model of the simplified
+/// clauses into a model of the original clauses.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Reconstruction {
+  /// For each removed clause, the literal which satisfies it and where it starts in literals
+  witnesses: Vec<(Literal, u32)>,
+  literals: Vec<Literal>,
+}
+
+impl Reconstruction {
+  pub const fn new() -> Self {
+    Self {
+      witnesses: vec![],
+      literals: vec![],
+    }
+  }
+  /// Records a removed clause, which can be satisfied by making witness true.
+  pub fn push(&mut self, witness: Literal, clause: &[Literal]) {
+    debug_assert!(clause.contains(&witness));
+    self.witnesses.push((witness, self.literals.len() as u32));
+    self.literals.extend_from_slice(clause);
+  }
+  /// Iterates over removed clauses and their witnesses, from the latest to the earliest.
+  pub fn iter(&self) -> impl Iterator<Item = (Literal, &[Literal])> + '_ {
+    (0..self.witnesses.len()).rev().map(move |i| {
+      let (witness, start) = self.witnesses[i];
+      let end = self
+        .witnesses
+        .get(i + 1)
+        .map_or(self.literals.len(), |&(_, end)| end as usize);
+      (witness, &self.literals[start as usize..end])
+    })
+  }
+  /// Extends assignments which satisfy the simplified clauses into ones which satisfy every
+  /// removed clause as well.
+  pub fn extend(&self, assignments: &mut [Option<bool>]) {
+    for (witness, clause) in self.iter() {
+      if clause.iter().all(|lit| lit.assn(assignments) != Some(true)) {
+        assignments[witness.var() as usize] = Some(witness.val());
+      }
+    }
+  }
+  pub fn is_empty(&self) -> bool { self.witnesses.is_empty() }
+  pub fn clear(&mut self) {
+    self.witnesses.clear();
+    self.literals.clear();
+  }
+}
+
+/// A set of clauses with occurrence lists, which can be simplified before search.
+#[derive(Debug)]
+pub struct Preprocessor {
+  /// Clauses being simplified, where removed clauses are left empty
+  clauses: Vec<Vec<Literal>>,
+  /// Whether each clause is implied by the other clauses
+  learnt: Vec<bool>,
+  /// literal -> indices of clauses containing it
+  occs: Vec<Vec<usize>>,
+
+  /// Values at level 0, including units found while preprocessing
+  assignments: Vec<Option<bool>>,
+  /// Units found while preprocessing
+  units: Vec<Literal>,
+  /// How many units have been removed from the clauses
+  propagated: usize,
+  /// Whether an empty clause was found
+  unsat: bool,
+
+  /// Variables removed from every clause by elimination or substitution
+  eliminated: Vec<bool>,
+}
+
+impl Preprocessor {
+  /// Creates an empty preprocessor with these values at level 0.
+  pub fn new(assignments: &[Option<bool>]) -> Self {
+    Self {
+      clauses: vec![],
+      learnt: vec![],
+      occs: vec![vec![]; assignments.len() << 1],
+      assignments: assignments.to_vec(),
+      units: vec![],
+      propagated: 0,
+      unsat: false,
+      eliminated: vec![false; assignments.len()],
+    }
+  }
+  /// Adds a clause, removing false literals and ignoring it if it is already satisfied.
+  /// Learnt clauses must be implied by the other clauses.
+  pub fn add_clause(&mut self, lits: &[Literal], learnt: bool) {
+    let mut clause = lits.to_vec();
+    clause.sort_unstable();
+    clause.dedup();
+    // a literal and its negation are adjacent when sorted
+    if clause.windows(2).any(|w| w[0].is_negation(w[1])) {
+      return;
+    }
+    let assns = &self.assignments;
+    if clause.iter().any(|lit| lit.assn(assns) == Some(true)) {
+      return;
+    }
+    clause.retain(|lit| lit.assn(assns).is_none());
+    match clause.len() {
+      0 => self.unsat = true,
+      1 => self.assign(clause[0]),
+      _ => {
+        let idx = self.clauses.len();
+        for lit in clause.iter() {
+          self.occs[lit.raw() as usize].push(idx);
+        }
+        self.clauses.push(clause);
+        self.learnt.push(learnt);
+      },
+    }
+  }
+  /// Is there an empty clause in this formula?
+  pub fn is_unsat(&self) -> bool { self.unsat }
+  /// Literals found to be true while preprocessing.
+  pub fn units(&self) -> &[Literal] { &self.units }
+  /// Every clause which has not been removed, and whether it is learnt.
+  pub fn clauses(&self) -> impl Iterator<Item = (&[Literal], bool)> + '_ {
+    self
+      .clauses
+      .iter()
+      .zip(self.learnt.iter())
+      .filter(|(clause, _)| !clause.is_empty())
+      .map(|(clause, &learnt)| (clause.as_slice(), learnt))
+  }
+  /// Was this variable removed from every clause by elimination or substitution?
+  pub fn is_eliminated(&self, var: u32) -> bool { self.eliminated[var as usize] }
+
+  fn assign(&mut self, lit: Literal) {
+    match lit.assn(&self.assignments) {
+      Some(true) => (),
+      Some(false) => self.unsat = true,
+      None => {
+        self.assignments[lit.var() as usize] = Some(lit.val());
+        self.units.push(lit);
+      },
+    }
+  }
+  /// Removes units from all clauses, returning false if there is a conflict.
+  fn propagate(&mut self) -> bool {
+    while !self.unsat && self.propagated < self.units.len() {
+      let lit = self.units[self.propagated];
+      self.propagated += 1;
+      for idx in take(&mut self.occs[lit.raw() as usize]) {
+        self.remove_clause(idx);
+      }
+      for idx in take(&mut self.occs[(!lit).raw() as usize]) {
+        self.strengthen(idx, !lit);
+      }
+    }
+    !self.unsat
+  }
+  /// Removes a literal from a clause, assigning the clause if it becomes a unit.
+  fn strengthen(&mut self, idx: usize, lit: Literal) {
+    let clause = &mut self.clauses[idx];
+    clause.retain(|&l| l != lit);
+    let occs = &mut self.occs[lit.raw() as usize];
+    if let Some(pos) = occs.iter().position(|&i| i == idx) {
+      occs.swap_remove(pos);
+    }
+    if clause.len() == 1 {
+      let unit = clause[0];
+      self.remove_clause(idx);
+      self.assign(unit);
+    }
+  }
+  fn remove_clause(&mut self, idx: usize) {
+    for lit in take(&mut self.clauses[idx]) {
+      let occs = &mut self.occs[lit.raw() as usize];
+      if let Some(pos) = occs.iter().position(|&i| i == idx) {
+        occs.swap_remove(pos);
+      }
+    }
+  }
+
+  /// Removes clauses which contain every literal of another clause, and strengthens clauses by
+  /// self-subsuming resolution, where a clause contains every literal of another except for
+  /// one which is negated, which is removed. Each clause is checked against every clause it
+  /// could subsume, so this covers both forward and backward subsumption.
+  /// Returns the number of clauses removed and strengthened.
+  pub fn subsume(&mut self) -> (usize, usize) {
+    if !self.propagate() {
+      return (0, 0);
+    }
+    let clauses = &self.clauses;
+    let mut queue = (0..clauses.len())
+      .filter(|&idx| !clauses[idx].is_empty())
+      .collect::<Vec<_>>();
+    // shortest clauses are popped first, since they subsume the most
+    queue.sort_by_key(|&idx| Reverse(clauses[idx].len()));
+    let mut subsumed = 0;
+    let mut strengthened = 0;
+    let mut candidates = vec![];
+    while let Some(idx) = queue.pop() {
+      if !self.propagate() {
+        break;
+      }
+      let clause = self.clauses[idx].clone();
+      if clause.is_empty() || clause.len() > SUBSUME_CLAUSE_LIMIT {
+        continue;
+      }
+      // any clause this subsumes must contain its least common variable
+      let occs = &self.occs;
+      let num_occs =
+        |lit: &Literal| occs[lit.raw() as usize].len() + occs[(!*lit).raw() as usize].len();
+      let lit = *clause.iter().min_by_key(|lit| num_occs(lit)).unwrap();
+      candidates.clear();
+      candidates.extend(
+        occs[lit.raw() as usize]
+          .iter()
+          .chain(occs[(!lit).raw() as usize].iter())
+          .copied()
+          .filter(|&other| other != idx),
+      );
+      for &other in candidates.iter() {
+        if self.clauses[other].len() < clause.len() {
+          continue;
+        }
+        match subsumes(&clause, &self.clauses[other]) {
+          None => (),
+          Some(None) => {
+            // the subsuming clause is needed in place of the one it removes
+            if !self.learnt[other] {
+              self.learnt[idx] = false;
+            }
+            self.remove_clause(other);
+            subsumed += 1;
+          },
+          Some(Some(removed)) => {
+            self.strengthen(other, removed);
+            strengthened += 1;
+            queue.push(other);
+          },
+        }
+      }
+    }
+    (subsumed, strengthened)
+  }
+
+  /// Finds equivalent literals as strongly connected components of the binary implication graph,
+  /// and replaces each with a representative of its component. Frozen variables are kept, and
+  /// are preferred as representatives. Returns the number of variables substituted.
+  pub fn substitute(&mut self, frozen: &[bool], rec: &mut Reconstruction) -> usize {
+    if !self.propagate() {
+      return 0;
+    }
+    let components = self.binary_components();
+    let num_lits = self.occs.len();
+    // the representative of each component, where a component's negation is also a component
+    let mut reprs = vec![Literal::INVALID; num_lits];
+    for raw in 0..num_lits as u32 {
+      let lit = Literal::from(raw);
+      let comp = components[raw as usize] as usize;
+      if components[(!lit).raw() as usize] as usize == comp {
+        self.unsat = true;
+        return 0;
+      }
+      let repr = &mut reprs[comp];
+      let key = |lit: Literal| (!frozen[lit.var() as usize], lit.var());
+      if !repr.is_valid() || key(lit) < key(*repr) {
+        *repr = lit;
+      }
+    }
+    let mut substitutes = vec![None; self.assignments.len()];
+    let mut substituted = 0;
+    for var in 0..self.assignments.len() as u32 {
+      let lit = Literal::new(var, false);
+      let repr = reprs[components[lit.raw() as usize] as usize];
+      let skip = frozen[var as usize] || self.eliminated[var as usize];
+      if skip || repr.var() == var || self.assignments[var as usize].is_some() {
+        continue;
+      }
+      rec.push(lit, &[lit, !repr]);
+      rec.push(!lit, &[!lit, repr]);
+      substitutes[var as usize] = Some(repr);
+      self.eliminated[var as usize] = true;
+      substituted += 1;
+    }
+    if substituted == 0 {
+      return 0;
+    }
+    let substitute = |lit: Literal| match substitutes[lit.var() as usize] {
+      None => lit,
+      Some(repr) if lit.negated() => !repr,
+      Some(repr) => repr,
+    };
+    let mut clause = vec![];
+    for idx in 0..self.clauses.len() {
+      if self.clauses[idx]
+        .iter()
+        .all(|lit| substitutes[lit.var() as usize].is_none())
+      {
+        continue;
+      }
+      clause.clear();
+      clause.extend(self.clauses[idx].iter().map(|&lit| substitute(lit)));
+      self.remove_clause(idx);
+      self.add_clause(&clause, self.learnt[idx]);
+    }
+    self.propagate();
+    substituted
+  }
+  /// Returns the strongly connected component of each literal in the graph of implications
+  /// from binary clauses, using Tarjan's algorithm.
+  fn binary_components(&self) -> Vec<u32> {
+    let num_lits = self.occs.len();
+    let mut implies = vec![vec![]; num_lits];
+    for clause in self.clauses.iter().filter(|clause| clause.len() == 2) {
+      implies[(!clause[0]).raw() as usize].push(clause[1]);
+      implies[(!clause[1]).raw() as usize].push(clause[0]);
+    }
+    let mut index = vec![UNVISITED; num_lits];
+    let mut low = vec![0; num_lits];
+    let mut on_stack = vec![false; num_lits];
+    let mut components = vec![UNVISITED; num_lits];
+    let mut stack = vec![];
+    // (literal, how many of its implications have been visited)
+    let mut calls = vec![];
+    let mut next_index = 0;
+    let mut next_component = 0;
+    for root in 0..num_lits {
+      if index[root] != UNVISITED {
+        continue;
+      }
+      calls.push((root, 0));
+      index[root] = next_index;
+      low[root] = next_index;
+      next_index += 1;
+      stack.push(root);
+      on_stack[root] = true;
+      while let Some(&mut (lit, ref mut visited)) = calls.last_mut() {
+        if let Some(&next) = implies[lit].get(*visited) {
+          *visited += 1;
+          let next = next.raw() as usize;
+          if index[next] == UNVISITED {
+            index[next] = next_index;
+            low[next] = next_index;
+            next_index += 1;
+            stack.push(next);
+            on_stack[next] = true;
+            calls.push((next, 0));
+          } else if on_stack[next] {
+            low[lit] = low[lit].min(index[next]);
+          }
+          continue;
+        }
+        calls.pop();
+        if low[lit] == index[lit] {
+          while let Some(member) = stack.pop() {
+            on_stack[member] = false;
+            components[member] = next_component;
+            if member == lit {
+              break;
+            }
+          }
+          next_component += 1;
+        }
+        if let Some(&(parent, _)) = calls.last() {
+          low[parent] = low[parent].min(low[lit]);
+        }
+      }
+    }
+    components
+  }
+
+  /// Removes blocked clauses, which contain a literal such that every resolvent on it with
+  /// another clause is a tautology. Blocking literals are never frozen.
+  /// Returns the number of clauses removed.
+  pub fn block(&mut self, frozen: &[bool], rec: &mut Reconstruction) -> usize {
+    if !self.propagate() {
+      return 0;
+    }
+    let mut queue = (0..self.clauses.len()).collect::<Vec<_>>();
+    let mut queued = vec![true; self.clauses.len()];
+    let mut blocked = 0;
+    while let Some(idx) = queue.pop() {
+      queued[idx] = false;
+      let clause = &self.clauses[idx];
+      let occs = &self.occs;
+      let clauses = &self.clauses;
+      let blocking = clause.iter().copied().find(|&lit| {
+        let resolved = &occs[(!lit).raw() as usize];
+        !frozen[lit.var() as usize]
+          && resolved.len() <= BLOCK_OCC_LIMIT
+          && resolved
+            .iter()
+            .all(|&other| is_tautology(clause, &clauses[other], lit.var()))
+      });
+      let blocking = match blocking {
+        None => continue,
+        Some(blocking) => blocking,
+      };
+      // learnt clauses are implied, so they never need to be satisfied again
+      if !self.learnt[idx] {
+        rec.push(blocking, clause);
+      }
+      // clauses resolving with this one may now be blocked as well
+      for lit in clause.iter() {
+        for &other in occs[(!*lit).raw() as usize].iter() {
+          if !queued[other] {
+            queued[other] = true;
+            queue.push(other);
+          }
+        }
+      }
+      self.remove_clause(idx);
+      blocked += 1;
+    }
+    blocked
+  }
+
+  /// Eliminates variables by replacing the clauses they are in with all their resolvents, as
+  /// long as that does not increase the number of clauses. Frozen variables are kept.
+  /// Returns the number of variables eliminated.
+  pub fn eliminate(&mut self, frozen: &[bool], rec: &mut Reconstruction) -> usize {
+    if !self.propagate() {
+      return 0;
+    }
+    let num_vars = self.assignments.len() as u32;
+    let occs = &self.occs;
+    let num_occs = |var: u32| {
+      (
+        occs[Literal::new(var, false).raw() as usize].len(),
+        occs[Literal::new(var, true).raw() as usize].len(),
+      )
+    };
+    let mut candidates = (0..num_vars)
+      .filter(|&var| !frozen[var as usize])
+      .filter(|&var| {
+        let (pos, neg) = num_occs(var);
+        pos + neg > 0 && pos <= ELIM_OCC_LIMIT && neg <= ELIM_OCC_LIMIT
+      })
+      .collect::<Vec<_>>();
+    // cheapest variables first, since they are most likely to be eliminated
+    candidates.sort_by_key(|&var| {
+      let (pos, neg) = num_occs(var);
+      pos * neg
+    });
+    let mut eliminated = 0;
+    let mut resolvents = vec![];
+    for var in candidates {
+      if self.assignments[var as usize].is_some() {
+        continue;
+      }
+      if self.try_eliminate(var, &mut resolvents, rec) {
+        eliminated += 1;
+      }
+      if !self.propagate() {
+        break;
+      }
+    }
+    eliminated
+  }
+  fn try_eliminate(
+    &mut self,
+    var: u32,
+    resolvents: &mut Vec<(Vec<Literal>, bool)>,
+    rec: &mut Reconstruction,
+  ) -> bool {
+    let pos_lit = Literal::new(var, false);
+    let pos = &self.occs[pos_lit.raw() as usize];
+    let neg = &self.occs[(!pos_lit).raw() as usize];
+    // occurrences may have grown from resolvents of earlier eliminations
+    if pos.len() > ELIM_OCC_LIMIT || neg.len() > ELIM_OCC_LIMIT {
+      return false;
+    }
+    resolvents.clear();
+    for &p in pos.iter() {
+      for &n in neg.iter() {
+        let resolvent = match resolve(&self.clauses[p], &self.clauses[n], var) {
+          None => continue,
+          Some(resolvent) => resolvent,
+        };
+        if resolvent.len() > ELIM_CLAUSE_LIMIT || resolvents.len() == pos.len() + neg.len() {
+          return false;
+        }
+        resolvents.push((resolvent, self.learnt[p] || self.learnt[n]));
+      }
+    }
+    // only the smaller side needs to be kept, with var defaulting to the other side
+    let (witness, kept) = if pos.len() <= neg.len() {
+      (pos_lit, pos)
+    } else {
+      (!pos_lit, neg)
+    };
+    for &idx in kept.iter() {
+      rec.push(witness, &self.clauses[idx]);
+    }
+    rec.push(!witness, &[!witness]);
+    let removed = pos.iter().chain(neg.iter()).copied().collect::<Vec<_>>();
+    for idx in removed {
+      self.remove_clause(idx);
+    }
+    for (resolvent, learnt) in resolvents.drain(..) {
+      self.add_clause(&resolvent, learnt);
+    }
+    self.eliminated[var as usize] = true;
+    true
+  }
+}
+
+/// Checks whether a clause subsumes another, where both are sorted. Returns Some(None) if every
+/// literal is in the other clause, and Some(Some(lit)) if exactly one literal is negated in the
+/// other clause as lit, which can then be removed from it.
+fn subsumes(clause: &[Literal], other: &[Literal]) -> Option<Option<Literal>> {
+  let mut negated = None;
+  let mut others = other.iter().peekable();
+  for &lit in clause {
+    // literals of the same variable are adjacent when sorted
+    while matches!(others.peek(), Some(o) if o.var() < lit.var()) {
+      others.next();
+    }
+    match others.next() {
+      Some(&o) if o == lit => (),
+      Some(&o) if o.var() == lit.var() && negated.is_none() => negated = Some(o),
+      _ => return None,
+    }
+  }
+  Some(negated)
+}
+
+/// Checks whether the resolvent of two sorted clauses on var contains a literal and its
+/// negation.
+fn is_tautology(clause: &[Literal], other: &[Literal], var: u32) -> bool {
+  let mut others = other.iter().peekable();
+  for &lit in clause.iter().filter(|lit| lit.var() != var) {
+    while matches!(others.peek(), Some(o) if o.var() < lit.var()) {
+      others.next();
+    }
+    if matches!(others.peek(), Some(o) if o.is_negation(lit)) {
+      return true;
+    }
+  }
+  false
+}
+
+/// Resolves two clauses on var, which must be positive in the first and negative in the
+/// second. Returns None if the resolvent is a tautology.
+fn resolve(pos: &[Literal], neg: &[Literal], var: u32) -> Option<Vec<Literal>> {
+  let mut out = pos
+    .iter()
+    .chain(neg.iter())
+    .filter(|lit| lit.var() != var)
+    .copied()
+    .collect::<Vec<_>>();
+  out.sort_unstable();
+  out.dedup();
+  if out.windows(2).any(|w| w[0].is_negation(w[1])) {
+    None
+  } else {
+    Some(out)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    test_util::{check_solver, dimacs_clauses, models, random_clauses, satisfies},
+    Options, Rng, Solver,
+  };
+
+  /// Simplifies random formulas, checking that every model of the result extends to a model of
+  /// the original clauses, and that the values of frozen variables in models are unchanged.
+  fn check_preprocessor(
+    seed: u64,
+    simplify: impl Fn(&mut Preprocessor, &[bool], &mut Reconstruction),
+  ) {
+    let mut rng = Rng::new(seed);
+    for i in 0..300 {
+      let vars = 1 + i % 10;
+      let num_clauses = rng.below(5 * vars) as usize;
+      let clauses = random_clauses(&mut rng, vars, num_clauses, 3);
+      let frozen = (0..vars).map(|_| rng.below(4) == 0).collect::<Vec<_>>();
+      let mut pre = Preprocessor::new(&vec![None; vars as usize]);
+      for clause in &clauses {
+        pre.add_clause(clause, false);
+      }
+      let mut rec = Reconstruction::new();
+      simplify(&mut pre, &frozen, &mut rec);
+
+      let expected = models(vars, &clauses);
+      if pre.is_unsat() {
+        assert!(expected.is_empty(), "{:?}", clauses);
+        continue;
+      }
+      let mut simplified = pre.clauses().map(|(c, _)| c.to_vec()).collect::<Vec<_>>();
+      simplified.extend(pre.units().iter().map(|&unit| vec![unit]));
+      let frozen_values = |model: &[Option<bool>]| {
+        model
+          .iter()
+          .zip(&frozen)
+          .filter(|(_, &frozen)| frozen)
+          .map(|(&v, _)| v)
+          .collect::<Vec<_>>()
+      };
+      let mut found = vec![];
+      for mut model in models(vars, &simplified) {
+        rec.extend(&mut model);
+        assert!(
+          satisfies(&model, &clauses),
+          "{:?} {:?}",
+          clauses,
+          simplified
+        );
+        found.push(frozen_values(&model));
+      }
+      for model in &expected {
+        assert!(
+          found.contains(&frozen_values(model)),
+          "{:?} {:?}",
+          clauses,
+          simplified
+        );
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_eliminate() {
+    check_preprocessor(1, |pre, frozen, rec| {
+      pre.eliminate(frozen, rec);
+    });
+  }
+
+  #[test]
+  pub fn test_solves_after_eliminating() {
+    let mut options = Options::new();
+    options.elim = true;
+    check_solver(&options, 1);
+  }
+
+  #[test]
+  pub fn test_subsume() {
+    check_preprocessor(2, |pre, _, _| {
+      pre.subsume();
+      if pre.is_unsat() {
+        return;
+      }
+      let clauses = pre.clauses().map(|(c, _)| c).collect::<Vec<_>>();
+      for (i, a) in clauses.iter().enumerate() {
+        for (j, b) in clauses.iter().enumerate() {
+          let subsumed = a.iter().all(|lit| b.contains(lit));
+          assert!(i == j || !subsumed, "{:?} subsumes {:?}", a, b);
+        }
+      }
+    });
+  }
+
+  #[test]
+  pub fn test_solves_after_subsuming() {
+    let mut options = Options::new();
+    options.subsume = true;
+    check_solver(&options, 2);
+  }
+  #[test]
+  pub fn test_substitute() {
+    check_preprocessor(4, |pre, frozen, rec| {
+      let mut clauses = pre.clauses().map(|(c, _)| c.to_vec()).collect::<Vec<_>>();
+      clauses.extend(pre.units().iter().map(|&unit| vec![unit]));
+      let vars = frozen.len() as u32;
+      pre.substitute(frozen, rec);
+      let expected = models(vars, &clauses);
+      for var in (0..vars).filter(|&var| pre.is_eliminated(var)) {
+        assert!(!frozen[var as usize]);
+        // some other literal has the same value in every model
+        let equivalent = (0..2 * vars).map(Literal::from).any(|lit| {
+          lit.var() != var
+            && expected
+              .iter()
+              .all(|model| lit.assn(model) == model[var as usize])
+        });
+        assert!(equivalent, "{} {:?}", var, clauses);
+      }
+    });
+  }
+
+  #[test]
+  pub fn test_solves_after_substituting() {
+    let mut options = Options::new();
+    options.substitute = true;
+    check_solver(&options, 4);
+  }
+
+  #[test]
+  pub fn test_block() {
+    check_preprocessor(5, |pre, frozen, rec| {
+      pre.block(frozen, rec);
+      if pre.is_unsat() {
+        return;
+      }
+      let clauses = pre.clauses().map(|(c, _)| c).collect::<Vec<_>>();
+      for clause in &clauses {
+        for &lit in clause.iter().filter(|lit| !frozen[lit.var() as usize]) {
+          let blocked = clauses
+            .iter()
+            .filter(|other| other.contains(&!lit))
+            .all(|other| clause.iter().any(|&l| l != lit && other.contains(&!l)));
+          assert!(!blocked, "{:?} is blocked on {}", clause, lit);
+        }
+      }
+    });
+  }
+
+  #[test]
+  pub fn test_solves_after_blocking() {
+    let mut options = Options::new();
+    options.block = true;
+    check_solver(&options, 5);
+  }
+
+  #[test]
+  pub fn test_probe() {
+    let mut rng = Rng::new(3);
+    for &hyper_binary in &[false, true] {
+      for i in 0..300 {
+        let vars = 1 + i % 10;
+        let num_clauses = rng.below(5 * vars) as usize;
+        let clauses = random_clauses(&mut rng, vars, num_clauses, 3);
+        let mut options = Options::new();
+        options.probe = true;
+        options.hyper_binary = hyper_binary;
+        let mut solver = Solver::with_options(options);
+        solver.resize(vars);
+        let expected = models(vars, &clauses);
+        if !clauses.iter().all(|clause| solver.add_clause(clause)) || !solver.probe() {
+          assert!(expected.is_empty(), "{:?}", clauses);
+          continue;
+        }
+        // units and resolvents found by probing are implied, so no models are lost or gained
+        let mut dumped = vec![];
+        solver.dump_dimacs(&mut dumped, true).unwrap();
+        assert_eq!(
+          models(vars, &dimacs_clauses(&dumped)),
+          expected,
+          "{:?}",
+          clauses
+        );
+      }
+    }
+  }
+
+  #[test]
+  pub fn test_solves_after_probing() {
+    let mut options = Options::new();
+    options.probe = true;
+    options.hyper_binary = true;
+    check_solver(&options, 3);
+  }
+}
@@ -0,0 +1,31 @@
+/// Small xorshift* generator, so that runs are reproducible without extra dependencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+  state: u64,
+}
+
+pub const DEFAULT_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+impl Rng {
+  /// Creates a generator from a seed, where 0 is the same as `DEFAULT_SEED`.
+  pub const fn new(seed: u64) -> Self {
+    // xorshift must never have a state of zero
+    let state = if seed == 0 { DEFAULT_SEED } else { seed };
+    Self { state }
+  }
+  pub fn next_u64(&mut self) -> u64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+    self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+  }
+  /// Returns a uniformly distributed float in [0, 1)
+  pub fn next_f32(&mut self) -> f32 { (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32 }
+  pub fn next_bool(&mut self) -> bool { self.next_u64() >> 63 == 1 }
+  /// Returns a number in [0, n)
+  pub fn below(&mut self, n: u32) -> u32 { (((self.next_u64() >> 32) * n as u64) >> 32) as u32 }
+}
+
+impl Default for Rng {
+  fn default() -> Self { Self::new(DEFAULT_SEED) }
+}
@@ -0,0 +1,251 @@
+use crate::{
+  approx::{cell_models, random_xor},
+  ApproxCounter, Rng, Solver,
+};
+
+/// Default closeness to uniform, as used by UniGen
+pub const DEFAULT_KAPPA: f64 = 0.638;
+/// Rounds of hashing which may fail to find a small cell before sampling gives up
+pub const MAX_ATTEMPTS: u32 = 20;
+
+/// Near-uniform sampler in the style of UniGen2 (Chakraborty et al. 2015).
+/// Random XOR constraints over a sampling set pick a random cell of solutions, which is
+/// enumerated if its size is within bounds, and distinct solutions are picked uniformly from it.
+/// Each cell yields several samples, as finding a cell is much more expensive than enumerating it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sampler {
+  /// Smaller values make samples closer to uniform, at the cost of enumerating larger cells.
+  pub kappa: f64,
+  /// Seed for the random XOR constraints and choices within cells
+  pub seed: u64,
+}
+
+impl Sampler {
+  pub const fn new() -> Self {
+    Self {
+      kappa: DEFAULT_KAPPA,
+      seed: crate::rng::DEFAULT_SEED,
+    }
+  }
+  /// Returns the expected size of a cell.
+  pub fn pivot(&self) -> f64 { (4.03 * (1.0 + 1.0 / self.kappa).powi(2)).ceil() }
+  /// Returns the smallest and largest cells which solutions are picked from.
+  pub fn bounds(&self) -> (usize, usize) {
+    let pivot = self.pivot();
+    let lo = (pivot / (1.0 + self.kappa)).floor() as usize;
+    let hi = (1.0 + (1.0 + self.kappa) * pivot).ceil() as usize;
+    (lo, hi)
+  }
+  /// Returns an iterator over random solutions, which are distributed nearly uniformly over
+  /// their projections onto the sampling set.
+  ///
+  /// Sampling variables are frozen, and XOR constraints and blocking clauses are only added to
+  /// copies of the solver, one for each cell.
+  pub fn samples<'a>(&self, solver: &'a mut Solver, sampling_set: &[u32]) -> Samples<'a> {
+    for &var in sampling_set {
+      solver.set_frozen(var, true);
+    }
+    let num_vars = solver.num_vars();
+    let (lo, hi) = self.bounds();
+    let mut samples = Samples {
+      num_vars,
+      sampling_set: sampling_set.to_vec(),
+      rng: Rng::new(self.seed),
+      lo,
+      hi,
+      solutions: None,
+      pending: vec![],
+      min_hashes: 0,
+      max_hashes: 0,
+      solver,
+    };
+    // solutions of formulas with few of them are picked from directly
+    let mut solutions = cell_models(&mut samples.solver.fork(), &[], sampling_set, hi + 1);
+    if solutions.len() <= hi {
+      for solution in solutions.iter_mut() {
+        solution.truncate(num_vars as usize);
+      }
+      samples.solutions = Some(solutions);
+      return samples;
+    }
+    let mut approx = ApproxCounter::new();
+    approx.seed = self.seed;
+    let count = approx.count(samples.solver, sampling_set);
+    let q = (count.log2() + (1.0 + approx.epsilon).log2() - self.pivot().log2()).ceil();
+    samples.max_hashes = (q.max(1.0) as usize).min(sampling_set.len());
+    samples.min_hashes = samples.max_hashes.saturating_sub(3).max(1);
+    samples
+  }
+}
+
+impl Default for Sampler {
+  fn default() -> Self { Self::new() }
+}
+
+/// Iterator over random solutions of a solver, created by `Sampler::samples`.
+#[derive(Debug)]
+pub struct Samples<'a> {
+  solver: &'a mut Solver,
+  /// Variables before any were added for hashing
+  num_vars: u32,
+  sampling_set: Vec<u32>,
+  rng: Rng,
+  /// Bounds on the size of cells which solutions are picked from
+  lo: usize,
+  hi: usize,
+  /// Every solution, if there are few enough to be picked from directly
+  solutions: Option<Vec<Vec<Option<bool>>>>,
+  /// Samples picked from the last cell which have not been yielded yet
+  pending: Vec<Vec<Option<bool>>>,
+  /// Range of the number of XOR constraints tried for each cell
+  min_hashes: usize,
+  max_hashes: usize,
+}
+
+impl Iterator for Samples<'_> {
+  type Item = Vec<Option<bool>>;
+  fn next(&mut self) -> Option<Self::Item> {
+    if let Some(solutions) = self.solutions.as_ref() {
+      if solutions.is_empty() {
+        return None;
+      }
+      let i = self.rng.below(solutions.len() as u32);
+      return Some(solutions[i as usize].clone());
+    }
+    if let Some(sample) = self.pending.pop() {
+      return Some(sample);
+    }
+    let mut hashes = vec![];
+    for _ in 0..MAX_ATTEMPTS {
+      hashes.clear();
+      let mut hashed = self.solver.fork();
+      for _ in 0..self.max_hashes {
+        hashes.push(random_xor(&mut hashed, &self.sampling_set, &mut self.rng));
+      }
+      for m in self.min_hashes..=self.max_hashes {
+        let mut cell = cell_models(&mut hashed, &hashes[..m], &self.sampling_set, self.hi + 1);
+        if cell.is_empty() || cell.len() < self.lo || self.hi < cell.len() {
+          continue;
+        }
+        // partially shuffle the cell to pick distinct solutions from it
+        let picks = self.lo.max(1);
+        for i in 0..picks {
+          let j = i + self.rng.below((cell.len() - i) as u32) as usize;
+          cell.swap(i, j);
+        }
+        cell.truncate(picks);
+        for sample in cell.iter_mut() {
+          sample.truncate(self.num_vars as usize);
+        }
+        self.pending = cell;
+        break;
+      }
+      if let Some(sample) = self.pending.pop() {
+        return Some(sample);
+      }
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    test_util::{models, random_clauses, satisfies},
+    Literal,
+  };
+
+  fn solver(vars: u32, clauses: &[Vec<Literal>]) -> Solver {
+    let mut solver = Solver::new();
+    solver.resize(vars);
+    for clause in clauses {
+      solver.add_clause(clause);
+    }
+    solver
+  }
+
+  #[test]
+  pub fn test_samples_are_models() {
+    let mut rng = Rng::new(1);
+    for i in 0..50 {
+      let vars = 1 + i % 8;
+      let num_clauses = rng.below(5 * vars) as usize;
+      let clauses = random_clauses(&mut rng, vars, num_clauses, 3);
+      let sampling_set = (0..vars).collect::<Vec<_>>();
+      let mut solver = solver(vars, &clauses);
+      let samples = Sampler::new()
+        .samples(&mut solver, &sampling_set)
+        .take(20)
+        .collect::<Vec<_>>();
+      let expected = models(vars, &clauses);
+      if expected.is_empty() {
+        assert!(samples.is_empty());
+        continue;
+      }
+      assert_eq!(samples.len(), 20, "{:?}", clauses);
+      for sample in &samples {
+        assert_eq!(sample.len(), vars as usize);
+        assert!(satisfies(sample, &clauses), "{:?}", clauses);
+      }
+    }
+  }
+
+  /// Samples many times from the projections of a formula's models, returning the chi-squared
+  /// statistic of how often each was sampled, divided by the degrees of freedom.
+  fn uniformity(vars: u32, clauses: &[Vec<Literal>], sampling_set: &[u32], seed: u64) -> f64 {
+    let mut projections = models(vars, clauses)
+      .into_iter()
+      .map(|model| {
+        sampling_set
+          .iter()
+          .map(|&var| model[var as usize])
+          .collect()
+      })
+      .collect::<Vec<Vec<_>>>();
+    projections.sort_unstable();
+    projections.dedup();
+    let per_projection = 20;
+    let mut counts = vec![0; projections.len()];
+    let mut sampler = Sampler::new();
+    sampler.seed = seed;
+    let mut solver = solver(vars, clauses);
+    for sample in sampler
+      .samples(&mut solver, sampling_set)
+      .take(per_projection * projections.len())
+    {
+      assert!(satisfies(&sample, clauses));
+      let projection = sampling_set
+        .iter()
+        .map(|&var| sample[var as usize])
+        .collect::<Vec<_>>();
+      counts[projections.binary_search(&projection).unwrap()] += 1;
+    }
+    let expected = per_projection as f64;
+    let chi_squared: f64 = counts
+      .iter()
+      .map(|&count| (count as f64 - expected).powi(2) / expected)
+      .sum();
+    chi_squared / (projections.len() - 1) as f64
+  }
+
+  #[test]
+  pub fn test_samples_are_near_uniform() {
+    let sampler = Sampler::new();
+    // few enough solutions to be picked from directly
+    let clauses = vec![vec![Literal::new(0, false), Literal::new(1, false)]];
+    assert!(uniformity(5, &clauses, &[0, 1, 2, 3, 4], 1) < 2.0);
+    let mut rng = Rng::new(2);
+    for i in 0..4 {
+      let vars = 8;
+      let clauses = random_clauses(&mut rng, vars, 3, 3);
+      // half of the formulas are sampled on a projection
+      let sampling_set = (0..vars)
+        .filter(|&var| i % 2 == 0 || var > 1)
+        .collect::<Vec<_>>();
+      assert!(models(vars, &clauses).len() > sampler.bounds().1);
+      let chi_squared = uniformity(vars, &clauses, &sampling_set, i + 1);
+      assert!(chi_squared < 2.0, "{} {:?}", chi_squared, clauses);
+    }
+  }
+}
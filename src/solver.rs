@@ -1,12 +1,26 @@
-use crate::{CRef, Database, Literal, RestartState, Stats, VariableState, WatchList};
-use hashbrown::{hash_map::Entry, HashMap};
+use crate::{
+  CRef, Database, DecisionHeuristic, Heuristic, Literal, ModeState, Options, PhaseState,
+  Preprocessor, Reconstruction, RestartPolicy, RestartState, Rng, SearchMode, Stats, WatchList,
+};
+use hashbrown::{hash_map::Entry, HashMap, HashSet};
 use rustc_hash::FxHasher;
-use std::{hash::BuildHasherDefault, io, mem::replace, path::Path};
+use std::{
+  hash::BuildHasherDefault,
+  io::{self, Write},
+  mem::{replace, take},
+  path::Path,
+};
 
 pub const RESTART_BASE: u64 = 100;
 pub const RESTART_INC: u64 = 2;
 pub const LEARNTSIZE_FACTOR: f32 = 1.0 / 3.0;
 pub const LEARNTSIZE_INC: f32 = 1.3;
+/// Conflicts between subsumption passes during search
+pub const SUBSUME_INTERVAL: u64 = 5000;
+/// Propagations allowed while probing
+pub const PROBE_PROPAGATIONS: u32 = 1_000_000;
+/// Conflicts between vivifying clauses
+pub const VIVIFY_INTERVAL: u64 = 2000;
 
 /// Reserved value that specifies an invalid value
 pub const INVALID_LEVEL: u32 = std::u32::MAX;
@@ -40,8 +54,49 @@ pub struct Solver {
   // TODO could compress this into a bit array
   polarities: Vec<bool>,
 
-  /// Var state independent decaying sum
-  var_state: VariableState,
+  /// Which variables can be picked as decisions
+  decision_vars: Vec<bool>,
+  /// Which variables must not be removed by preprocessing
+  frozen: Vec<bool>,
+  /// Which variables were removed from every clause by preprocessing
+  eliminated: Vec<bool>,
+
+  /// Has a conflict been found without any assumptions
+  unsat: bool,
+  /// Literals decided before any other decision in the current call to solve
+  assumptions: Vec<Literal>,
+  /// Assumptions which together made the last call to solve unsatisfiable
+  failed_assumptions: Vec<Literal>,
+  /// Variables which are decided before any others while enumerating projected models
+  projection: Option<Vec<u32>>,
+
+  /// Clauses removed by preprocessing, needed to extend the model
+  reconstruction: Reconstruction,
+  /// Has preprocessing already run
+  preprocessed: bool,
+  /// After how many conflicts to next run subsumption
+  next_subsume: u64,
+  /// After how many conflicts to next vivify clauses
+  next_vivify: u64,
+  /// Ticks spent when clauses were last vivified
+  vivified_ticks: u64,
+  /// Clauses which were already vivified, which are tried after every other clause
+  vivified: HashSet<CRef, BuildHasherDefault<FxHasher>>,
+  /// Length of the trail when satisfied clauses were last removed at level 0
+  simplified_trail: usize,
+  /// Assignments extended to variables removed by preprocessing, after a solution is found
+  model: Vec<Option<bool>>,
+
+  /// Target and best phases, and when to next rephase
+  phases: PhaseState,
+
+  /// Source of randomness for this solver
+  rng: Rng,
+
+  /// Heuristic for picking decision variables, VSIDS by default
+  var_state: Box<dyn DecisionHeuristic>,
+  /// Heuristic for the search mode which is not currently running, if switching modes
+  inactive_state: Option<Box<dyn DecisionHeuristic>>,
 
   /// which level is this solver currently at
   level: u32,
@@ -49,6 +104,9 @@ pub struct Solver {
   /// Restart State using Luby
   restart_state: RestartState,
 
+  /// When to switch between focused and stable search
+  mode_state: ModeState,
+
   // a reusable tracker for what was seen and what was not
   // should be clear before and after each call to analyze
   analyze_seen: HashMap<u32, SeenState, BuildHasherDefault<FxHasher>>,
@@ -56,15 +114,31 @@ pub struct Solver {
   /// Statistics for this solver
   pub stats: Stats,
 
+  /// Parameters for this solver
+  pub options: Options,
+
   learnt_buf: Vec<Literal>,
+  level_buf: Vec<u32>,
   unit_buf: Vec<(CRef, Literal)>,
+  /// Literals kept out of order when backtracking, which must be propagated again
+  kept_buf: Vec<Literal>,
   cref_buf: Vec<CRef>,
   seen_stack: Vec<(u32, Literal)>,
+  /// Literals implied by the last probe
+  probe_buf: Vec<Literal>,
+  /// Parent and depth of each variable implied by the last probe, in the tree of binary
+  /// implications rooted at the probed literal
+  probe_tree: HashMap<u32, (Literal, u32), BuildHasherDefault<FxHasher>>,
+  /// variables which still need to be resolved while shrinking a block of a learnt clause
+  shrink_open: HashSet<u32, BuildHasherDefault<FxHasher>>,
 }
 
 impl Solver {
   /// Creates a new instance of this solver
-  pub fn new() -> Self {
+  pub fn new() -> Self { Self::with_options(Options::new()) }
+  /// Creates a new instance of this solver with the given parameters
+  pub fn with_options(options: Options) -> Self {
+    let (var_state, inactive_state) = Self::heuristics(&options);
     Self {
       assignments: vec![],
       assignment_trail: vec![],
@@ -74,41 +148,266 @@ impl Solver {
       watch_list: WatchList::new(),
       database: Database::new(),
       polarities: vec![],
-      var_state: VariableState::new(),
+      decision_vars: vec![],
+      frozen: vec![],
+      eliminated: vec![],
+      unsat: false,
+      assumptions: vec![],
+      failed_assumptions: vec![],
+      projection: None,
+      reconstruction: Reconstruction::new(),
+      preprocessed: false,
+      next_subsume: SUBSUME_INTERVAL,
+      next_vivify: VIVIFY_INTERVAL,
+      vivified_ticks: 0,
+      vivified: HashSet::with_hasher(Default::default()),
+      simplified_trail: 0,
+      model: vec![],
+      phases: PhaseState::new(options.rephase_interval),
+      rng: Rng::new(options.seed),
+      var_state,
+      inactive_state,
       level: 0,
-      restart_state: RestartState::new(RESTART_BASE, RESTART_INC),
+      restart_state: Self::restart_state(&options),
+      mode_state: ModeState::new(&options),
 
       analyze_seen: HashMap::with_hasher(Default::default()),
       stats: Stats::new(),
       learnt_buf: vec![],
+      level_buf: vec![],
       unit_buf: vec![],
+      kept_buf: vec![],
       cref_buf: vec![],
       seen_stack: vec![],
+      probe_buf: vec![],
+      probe_tree: HashMap::with_hasher(Default::default()),
+      shrink_open: HashSet::with_hasher(Default::default()),
+      options,
+    }
+  }
+  /// Creates the active and inactive heuristics for these options
+  fn heuristics(
+    options: &Options,
+  ) -> (
+    Box<dyn DecisionHeuristic>,
+    Option<Box<dyn DecisionHeuristic>>,
+  ) {
+    let seed = if options.random_init_activity {
+      Some(options.seed)
+    } else {
+      None
+    };
+    if options.mode_switching {
+      (
+        Heuristic::Vmtf.build(seed),
+        Some(options.heuristic.build(seed)),
+      )
+    } else {
+      (options.heuristic.build(seed), None)
+    }
+  }
+  fn restart_state(options: &Options) -> RestartState {
+    let mut restart_state = RestartState::new(RESTART_BASE, RESTART_INC);
+    if options.mode_switching {
+      restart_state.policy = RestartPolicy::Ema;
     }
+    restart_state
   }
   /// Attempt to find a satisfying assignment for the current solver.
   /// Returning true if there is a solution found.
-  pub fn solve(&mut self) -> bool {
-    assert_eq!(self.level, 0);
+  pub fn solve(&mut self) -> bool { self.solve_with_assumptions(&[]) }
+  /// Attempt to find a satisfying assignment where every assumption is true.
+  /// If there is none, `failed_assumptions` returns which assumptions caused it, and if it is
+  /// empty there is no solution under any assumptions.
+  /// Clauses can be added between calls, so the same solver can answer many related queries.
+  pub fn solve_with_assumptions(&mut self, assumptions: &[Literal]) -> bool {
+    self.failed_assumptions.clear();
+    if self.unsat {
+      return false;
+    }
+    self.backtrack_to(0);
+    if self.propagate_kept().is_some() {
+      self.unsat = true;
+      return false;
+    }
+    let max_var = assumptions
+      .iter()
+      .map(|lit| lit.var() + 1)
+      .max()
+      .unwrap_or(0);
+    if max_var > self.num_vars() {
+      self.resize(max_var);
+      self.database.max_var = max_var;
+    }
+    for lit in assumptions {
+      if !self.preprocessed {
+        self.frozen[lit.var() as usize] = true;
+      }
+      assert!(
+        !self.eliminated[lit.var() as usize],
+        "Assumed variables must be frozen before preprocessing"
+      );
+    }
+    if self.options.preprocessing() && !self.preprocessed && !self.preprocess() {
+      self.unsat = true;
+      return false;
+    }
+    self.assumptions.clear();
+    self.assumptions.extend_from_slice(assumptions);
+    let sat = self.search();
+    if !sat && self.failed_assumptions.is_empty() {
+      self.unsat = true;
+    }
+    sat
+  }
+  /// Returns an iterator over solutions, which after each solution adds a clause blocking it.
+  /// If projection is given, solutions are only blocked on those variables, so each yielded
+  /// solution differs on at least one of them. The blocking clauses are kept afterwards.
+  ///
+  /// Projected variables are decided before any others, so that every other variable is
+  /// implied by them or decided afterwards, and blocking clauses only need the decisions which
+  /// imply the projected values.
+  pub fn enumerate_models(&mut self, projection: Option<&[u32]>) -> Models<'_> {
+    self.enumerate_models_with_assumptions(&[], projection)
+  }
+  /// Returns an iterator over solutions where every assumption is true.
+  /// Blocking clauses contain the negation of every assumption, so they are only active when
+  /// all of the same assumptions are made again.
+  pub fn enumerate_models_with_assumptions(
+    &mut self,
+    assumptions: &[Literal],
+    projection: Option<&[u32]>,
+  ) -> Models<'_> {
+    // blocking clauses are added after preprocessing, so their variables must be kept
+    if !self.preprocessed {
+      match projection {
+        Some(vars) => {
+          for &var in vars {
+            self.frozen[var as usize] = true;
+          }
+        },
+        None => {
+          for frozen in self.frozen.iter_mut() {
+            *frozen = true;
+          }
+        },
+      }
+    }
+    self.projection = projection.map(|vars| vars.to_vec());
+    Models {
+      solver: self,
+      assumptions: assumptions.to_vec(),
+      blocking: vec![],
+      done: false,
+    }
+  }
+  /// Writes a clause blocking the projection of the current solution, made of the negated
+  /// decisions which the projected variables were implied by.
+  /// If there is no projection, every assigned variable is projected.
+  fn blocking_clause(&mut self, out: &mut Vec<Literal>) {
+    let mut seen = replace(
+      &mut self.analyze_seen,
+      HashMap::with_hasher(Default::default()),
+    );
+    debug_assert!(seen.is_empty());
+    // variables assigned at level 0 are the same in every solution
+    let levels = &self.levels;
+    let implied = |var: &u32| !matches!(levels[*var as usize], 0 | INVALID_LEVEL);
+    let source = |var| (var, SeenState::Source);
+    match &self.projection {
+      None => seen.extend((0..self.num_vars()).filter(implied).map(source)),
+      Some(vars) => seen.extend(vars.iter().copied().filter(implied).map(source)),
+    }
+    for &lit in self.assignment_trail.iter().rev() {
+      if seen.is_empty() {
+        break;
+      }
+      if seen.remove(&lit.var()).is_none() {
+        continue;
+      }
+      match self.causes[lit.var() as usize] {
+        None => out.push(!lit),
+        Some(reason) => {
+          for l in reason.iter(&self.database) {
+            if l.var() != lit.var() && self.levels[l.var() as usize] != 0 {
+              seen.insert(l.var(), SeenState::Source);
+            }
+          }
+        },
+      }
+    }
+    seen.clear();
+    self.analyze_seen = seen;
+  }
+  /// Returns the assumptions which made the last call to `solve_with_assumptions`
+  /// unsatisfiable. Assuming any superset of them is also unsatisfiable.
+  pub fn failed_assumptions(&self) -> &[Literal] { &self.failed_assumptions }
+  /// Searches for a solution after assumptions are set, returning false with an empty set of
+  /// failed assumptions if there is no solution at all.
+  fn search(&mut self) -> bool {
     let mut max_learnts = (self.database.num_clauses as f32) * LEARNTSIZE_FACTOR;
 
-    while self.has_unassigned_vars() {
+    loop {
+      let lit = if (self.level as usize) < self.assumptions.len() {
+        let lit = self.assumptions[self.level as usize];
+        match lit.assn(&self.assignments) {
+          // levels are kept for assumptions which are already true, so each assumption stays
+          // at the level of its index
+          Some(true) => {
+            self.next_level();
+            continue;
+          },
+          Some(false) => {
+            self.analyze_final(lit);
+            return false;
+          },
+          None => lit,
+        }
+      } else if !self.has_unassigned_vars() {
+        break;
+      } else {
+        match self.choose_lit() {
+          // only non-decision variables are left unassigned
+          None => break,
+          Some(lit) => lit,
+        }
+      };
       self.next_level();
-      let lit = self.choose_lit();
       let mut conflict = self.with(lit, None);
 
       // loop as long as there is a conflict
       while let Some(clause) = conflict {
         self.restart_state.mark_conflict();
 
+        // with chronological backtracking the conflict may be below the current level
+        let (conflict_level, implied) = self.conflict_level(&clause);
         // Conflict when we can't backtrack any more.
-        if self.level == 0 {
+        if conflict_level == 0 {
           return false;
         }
+        // the trail before the current level has no conflicts
+        let conflict_free = self.level_indeces[self.level as usize - 1] as usize;
+        self.phases.update(&self.assignment_trail[..conflict_free]);
+        if let Some(lit) = implied {
+          // The clause should have implied its only literal at the conflict level.
+          self.backtrack_to(conflict_level - 1);
+          conflict = self.with(lit, Some(clause));
+          continue;
+        }
+        self.backtrack_to(conflict_level);
         self.stats.record_learned_clause();
         let (learnt_clause, backtrack_lvl) = self.analyze(&clause, self.level);
         debug_assert!(backtrack_lvl < self.level);
-        self.backtrack_to(backtrack_lvl);
+        let lbd = self.lbd(&learnt_clause);
+        self.restart_state.record_lbd(lbd);
+        match self.options.chrono_threshold {
+          Some(threshold) if self.level - backtrack_lvl > threshold => {
+            self.stats.record_chrono_backtrack();
+            let lvl = self.level - 1;
+            self.backtrack_to(lvl);
+          },
+          _ => self.backtrack_to(backtrack_lvl),
+        }
         if learnt_clause.is_empty() {
           return false;
         }
@@ -132,56 +431,629 @@ impl Solver {
       if self.restart_state.restart_suggested() {
         self.stats.record_restart();
         self.restart_state.restart();
-        self.backtrack_to(0);
+        let lvl = if self.options.reuse_trail && !self.needs_full_restart(max_learnts) {
+          self.reuse_level()
+        } else {
+          0
+        };
+        if lvl > 0 {
+          self.stats.record_reused_trail();
+        }
+        self.backtrack_to(lvl);
+        if lvl == 0 {
+          if self.propagate_kept().is_some() {
+            return false;
+          }
+          if self.inactive_state.is_some() {
+            self.maybe_switch_mode();
+          }
+          let conflicts = self.stats.clauses_learned as u64;
+          if self.options.rephase && self.phases.rephase_suggested(conflicts) {
+            self
+              .phases
+              .rephase(conflicts, &mut self.polarities, &mut self.rng);
+          }
+          if self.options.vivify && conflicts >= self.next_vivify {
+            self.next_vivify = conflicts + VIVIFY_INTERVAL;
+            if !self.vivify() {
+              return false;
+            }
+          }
+        }
       }
 
       if self.level == 0 {
-        self.watch_list.remove_satisfied(&self.assignments);
+        // clauses can only have become satisfied if there are new units
+        if self.assignment_trail.len() != self.simplified_trail {
+          self.simplified_trail = self.assignment_trail.len();
+          self.watch_list.remove_satisfied(&self.assignments);
+        }
+        let conflicts = self.stats.clauses_learned as u64;
+        if self.options.subsume && conflicts >= self.next_subsume {
+          self.next_subsume = conflicts + SUBSUME_INTERVAL;
+          let mut pre = self.preprocessor();
+          self.subsume(&mut pre);
+          if !self.load_preprocessed(&pre) {
+            return false;
+          }
+        }
       }
 
       if self.level == 0 && self.stats.clauses_learned > (max_learnts as usize) {
-        let crefs = self.watch_list.drain().filter_map(|(l_0, l_1, cref)| {
-          debug_assert_ne!(l_0, l_1);
-          if l_0 < l_1 {
-            Some(cref)
-          } else {
-            None
-          }
-        });
-        self.cref_buf.extend(crefs);
-        let new_crefs = self
-          .database
-          .compact(&self.assignments, self.cref_buf.drain(..));
-        self.unit_buf.clear();
-        for (cref, l_0, l_1) in new_crefs {
-          if !l_1.is_valid() {
-            self.unit_buf.push((cref, l_0));
-            continue;
-          }
-          let unit = self.watch_list.watch_with_lits(cref, l_0, l_1);
-          assert!(
-            unit.is_none(),
-            "INTERNAL ERROR there shouldn't be any unit clauses when compacting"
-          );
-        }
-        if self.with_units_from_buf().is_some() {
+        if !self.compact() {
           return false;
         }
         max_learnts *= LEARNTSIZE_INC;
       }
     }
+    self.extend_model();
+    true
+  }
+
+  /// Removes satisfied clauses and false literals from the database at level 0, then
+  /// propagates any clauses which became units. Returns false if there is a conflict.
+  fn compact(&mut self) -> bool {
+    debug_assert_eq!(self.level, 0);
+    let crefs = self.watch_list.drain().filter_map(|(l_0, l_1, cref)| {
+      debug_assert_ne!(l_0, l_1);
+      if l_0 < l_1 {
+        Some(cref)
+      } else {
+        None
+      }
+    });
+    self.cref_buf.extend(crefs);
+    // clauses are moved, so they no longer match the ones which were vivified
+    self.vivified.clear();
+    let new_crefs = self
+      .database
+      .compact(&self.assignments, self.cref_buf.drain(..));
+    self.unit_buf.clear();
+    for (cref, l_0, l_1) in new_crefs {
+      if !l_1.is_valid() {
+        self.unit_buf.push((cref, l_0));
+        continue;
+      }
+      let unit = self.watch_list.watch_with_lits(cref, l_0, l_1);
+      assert!(
+        unit.is_none(),
+        "INTERNAL ERROR there shouldn't be any unit clauses when compacting"
+      );
+    }
+    self.with_units_from_buf().is_none()
+  }
+
+  /// Writes the watched clauses of this solver as DIMACs, along with units assigned at level 0.
+  /// Clauses satisfied at level 0 are left out, as are literals false at level 0.
+  /// Learnt clauses are implied by the other clauses, so they can be left out.
+  /// If the clauses are known to be unsatisfiable, only an empty clause is written.
+  pub fn dump_dimacs(&self, w: &mut impl Write, include_learnts: bool) -> io::Result<()> {
+    if self.unsat {
+      writeln!(w, "p cnf {} 1", self.num_vars())?;
+      return writeln!(w, "0");
+    }
+    let levels = &self.levels;
+    let assns = &self.assignments;
+    let db = &self.database;
+    let fixed = move |lit: &Literal| levels[lit.var() as usize] == 0;
+    let units = || self.assignment_trail.iter().filter(move |lit| fixed(lit));
+    let clauses = || {
+      self
+        .watch_list
+        .clauses()
+        .filter(move |cref| include_learnts || !cref.is_learnt())
+        .filter(move |cref| {
+          !cref
+            .iter(db)
+            .any(|lit| fixed(lit) && lit.assn(assns) == Some(true))
+        })
+    };
+    let num_clauses = units().count() + clauses().count();
+    writeln!(w, "p cnf {} {}", self.num_vars(), num_clauses)?;
+    for lit in units() {
+      writeln!(w, "{} 0", lit)?;
+    }
+    for cref in clauses() {
+      for lit in cref.iter(db).filter(|lit| !fixed(lit)) {
+        write!(w, "{} ", lit)?;
+      }
+      writeln!(w, "0")?;
+    }
+    Ok(())
+  }
+  /// Simplifies the clauses by propagating at level 0, removing satisfied clauses, and with the
+  /// preprocessing techniques enabled in the solver's options. Then writes the remaining
+  /// clauses and level 0 units as DIMACs to cnf, and the clauses needed to extend a model of
+  /// them into a model of the original clauses to map.
+  ///
+  /// Each line of map is a witness literal followed by a removed clause. To extend a model,
+  /// go through the lines in order, and make the witness true if its clause is not satisfied.
+  /// Returns false if the clauses are unsatisfiable, in which case an empty clause is written.
+  pub fn write_simplified<W: Write, M: Write>(
+    &mut self,
+    cnf: &mut W,
+    map: &mut M,
+  ) -> io::Result<bool> {
+    assert_eq!(self.level, 0);
+    let sat = !self.unsat
+      && (!self.options.preprocessing() || self.preprocessed || self.preprocess())
+      && self.compact();
+    if !sat {
+      self.unsat = true;
+      writeln!(cnf, "p cnf {} 1", self.num_vars())?;
+      writeln!(cnf, "0")?;
+      return Ok(false);
+    }
+    let assns = &self.assignments;
+    let db = &self.database;
+    // units found after compacting are not removed from clauses yet
+    let clauses = || {
+      self
+        .watch_list
+        .clauses()
+        .filter(move |cref| cref.iter(db).all(|lit| lit.assn(assns) != Some(true)))
+    };
+    let num_clauses = self.assignment_trail.len() + clauses().count();
+    writeln!(cnf, "p cnf {} {}", self.num_vars(), num_clauses)?;
+    for lit in self.assignment_trail.iter() {
+      writeln!(cnf, "{} 0", lit)?;
+    }
+    for cref in clauses() {
+      for lit in cref.iter(db).filter(|lit| lit.assn(assns).is_none()) {
+        write!(cnf, "{} ", lit)?;
+      }
+      writeln!(cnf, "0")?;
+    }
+    for (witness, clause) in self.reconstruction.iter() {
+      write!(map, "{}", witness)?;
+      for lit in clause {
+        write!(map, " {}", lit)?;
+      }
+      writeln!(map, " 0")?;
+    }
+    Ok(true)
+  }
+
+  /// Simplifies the clauses at level 0 with the preprocessing techniques enabled in the
+  /// solver's options. Eliminated variables are no longer decisions, and are assigned when the
+  /// model is extended. Returns false if the clauses are unsatisfiable.
+  pub fn preprocess(&mut self) -> bool {
+    assert_eq!(self.level, 0);
+    self.preprocessed = true;
+    if self.options.probe && !self.probe() {
+      return false;
+    }
+    let mut pre = self.preprocessor();
+    if self.options.subsume {
+      self.subsume(&mut pre);
+    }
+    if self.options.substitute {
+      let substituted = pre.substitute(&self.frozen, &mut self.reconstruction);
+      self.stats.record_substituted_vars(substituted as u32);
+    }
+    if self.options.block {
+      let blocked = pre.block(&self.frozen, &mut self.reconstruction);
+      self.stats.record_blocked_clauses(blocked as u32);
+    }
+    if self.options.elim {
+      let eliminated = pre.eliminate(&self.frozen, &mut self.reconstruction);
+      self.stats.record_eliminated_vars(eliminated as u32);
+    }
+    self.load_preprocessed(&pre)
+  }
+  /// Assigns each unassigned decision variable both ways at level 1. Literals which lead to a
+  /// conflict have failed, so their negation is forced, and literals implied by both values
+  /// are forced as well. Returns false if the clauses are unsatisfiable.
+  pub fn probe(&mut self) -> bool {
+    assert_eq!(self.level, 0);
+    let start = self.stats.propogations;
+    let mut implied = vec![false; self.num_vars() as usize * 2];
+    for var in 0..self.num_vars() {
+      if self.stats.propogations - start > PROBE_PROPAGATIONS {
+        break;
+      }
+      if !self.decision_vars[var as usize] || self.assignments[var as usize].is_some() {
+        continue;
+      }
+      let lit = Literal::new(var, false);
+      if self.probe_lit(lit) {
+        self.stats.record_failed_literal();
+        if !self.force(!lit) {
+          return false;
+        }
+        continue;
+      }
+      let pos_implied = take(&mut self.probe_buf);
+      let failed = self.probe_lit(!lit);
+      for l in pos_implied.iter() {
+        implied[l.raw() as usize] = true;
+      }
+      let common = self
+        .probe_buf
+        .iter()
+        .copied()
+        .filter(|l| implied[l.raw() as usize])
+        .collect::<Vec<_>>();
+      for l in pos_implied.iter() {
+        implied[l.raw() as usize] = false;
+      }
+      self.probe_buf = pos_implied;
+      if failed {
+        self.stats.record_failed_literal();
+        if !self.force(lit) {
+          return false;
+        }
+        continue;
+      }
+      for l in common {
+        self.stats.record_probed_unit();
+        if !self.force(l) {
+          return false;
+        }
+      }
+    }
     true
   }
+  /// Assigns lit at level 1 and propagates it, then backtracks to level 0.
+  /// Returns whether there was a conflict, leaving the implied literals in probe_buf otherwise.
+  fn probe_lit(&mut self, lit: Literal) -> bool {
+    debug_assert_eq!(self.level, 0);
+    self.next_level();
+    let failed = self.with(lit, None).is_some();
+    self.probe_buf.clear();
+    if !failed {
+      let start = self.level_indeces[0] as usize + 1;
+      self
+        .probe_buf
+        .extend_from_slice(&self.assignment_trail[start..]);
+    }
+    let mut resolvents = vec![];
+    if !failed && self.options.hyper_binary {
+      self.hyper_binary_resolvents(lit, &mut resolvents);
+    }
+    self.backtrack_to(0);
+    for (dominator, implied) in resolvents {
+      self.stats.record_hyper_binary();
+      let cref = self.database.add_learnt_from_slice(&[!dominator, implied]);
+      let unit = self.watch_list.watch(cref, &self.database);
+      debug_assert!(unit.is_none());
+    }
+    failed
+  }
+  /// Finds a binary clause for each literal implied by a longer clause while probing lit. The
+  /// binary clause is from the dominator of the clause's other literals in the tree of binary
+  /// implications, which implies the literal on its own. Binary clauses from any ancestor of
+  /// the dominator, such as lit itself, follow from it, so they are not added.
+  fn hyper_binary_resolvents(&mut self, lit: Literal, resolvents: &mut Vec<(Literal, Literal)>) {
+    let mut tree = replace(
+      &mut self.probe_tree,
+      HashMap::with_hasher(Default::default()),
+    );
+    tree.clear();
+    tree.insert(lit.var(), (lit, 0));
+    let ancestor = |tree: &HashMap<_, (Literal, u32), _>, mut a: Literal, mut b: Literal| {
+      while a != b {
+        let (parent_a, depth_a) = tree[&a.var()];
+        let (parent_b, depth_b) = tree[&b.var()];
+        if depth_a >= depth_b {
+          a = parent_a;
+        }
+        if depth_b >= depth_a {
+          b = parent_b;
+        }
+      }
+      a
+    };
+    for &implied in self.probe_buf.iter() {
+      let reason = self.causes[implied.var() as usize].expect("Implied literal without reason");
+      let levels = &self.levels;
+      let mut parents = reason
+        .iter(&self.database)
+        .filter(|l| l.var() != implied.var() && levels[l.var() as usize] > 0)
+        .map(|&l| !l);
+      let first = parents.next().unwrap_or(lit);
+      let dominator = parents.fold(first, |dom, parent| ancestor(&tree, dom, parent));
+      if reason.len() > 2 {
+        resolvents.push((dominator, implied));
+      }
+      let depth = tree[&dominator.var()].1 + 1;
+      tree.insert(implied.var(), (dominator, depth));
+    }
+    self.probe_tree = tree;
+  }
+  /// Shortens clauses by assigning the negation of their literals one at a time, each at a new
+  /// level. Once that leads to a conflict or implies one of the clause's literals, the clause
+  /// only needs the literals assigned so far. Literals which were implied to be false are
+  /// dropped as well. Learnt clauses are tried first, and clauses which were already tried are
+  /// only tried again once every other clause has been. Stops once its ticks exceed a fraction
+  /// of the ticks spent searching since the last time, set in the solver's options.
+  /// Must be called at level 0, and returns false if the clauses are unsatisfiable.
+  fn vivify(&mut self) -> bool {
+    debug_assert_eq!(self.level, 0);
+    let start = self.stats.ticks;
+    let budget = ((start - self.vivified_ticks) as f32 * self.options.vivify_effort) as u64;
+    let mut crefs = take(&mut self.cref_buf);
+    crefs.clear();
+    crefs.extend(self.watch_list.clauses().filter(|cref| cref.len() > 2));
+    if crefs.iter().all(|cref| self.vivified.contains(cref)) {
+      self.vivified.clear();
+    }
+    let vivified = &self.vivified;
+    crefs.sort_unstable_by_key(|cref| (vivified.contains(cref), !cref.is_learnt(), cref.len()));
+    let mut lits = take(&mut self.learnt_buf);
+    let mut sat = true;
+    for &cref in crefs.iter() {
+      if self.stats.ticks - start > budget {
+        break;
+      }
+      self.vivified.insert(cref);
+      lits.clear();
+      let mut satisfied = false;
+      for i in 0..cref.len() {
+        let lit = cref.as_slice(&self.database)[i];
+        match lit.assn(&self.assignments) {
+          Some(false) => continue,
+          Some(true) => {
+            satisfied = self.levels[lit.var() as usize] == 0;
+            lits.push(lit);
+            break;
+          },
+          None => {
+            lits.push(lit);
+            self.next_level();
+            if self.with(!lit, None).is_some() {
+              break;
+            }
+          },
+        }
+      }
+      self.backtrack_to(0);
+      if satisfied || lits.len() == cref.len() {
+        continue;
+      }
+      self
+        .stats
+        .record_vivified_clause((cref.len() - lits.len()) as u32);
+      // the old clause is no longer watched, so it is dropped when the database is compacted
+      self.watch_list.unwatch(cref, &self.database);
+      if lits.len() == 1 {
+        if !self.force(lits[0]) {
+          sat = false;
+          break;
+        }
+        continue;
+      }
+      let cref = if cref.is_learnt() {
+        self.database.add_learnt_from_slice(&lits)
+      } else {
+        self.database.add_clause_from_slice(&lits)
+      };
+      self.vivified.insert(cref);
+      let unit = self.watch_list.watch(cref, &self.database);
+      debug_assert!(unit.is_none());
+    }
+    self.vivified_ticks = self.stats.ticks;
+    lits.clear();
+    self.learnt_buf = lits;
+    crefs.clear();
+    self.cref_buf = crefs;
+    sat
+  }
+  /// Assigns lit at level 0 with a unit clause, returning false if there is a conflict.
+  fn force(&mut self, lit: Literal) -> bool {
+    debug_assert_eq!(self.level, 0);
+    match lit.assn(&self.assignments) {
+      Some(true) => true,
+      Some(false) => false,
+      None => {
+        let cref = self.database.add_learnt_from_slice(&[lit]);
+        self.with(lit, Some(cref)).is_none()
+      },
+    }
+  }
+  /// Removes subsumed clauses and strengthens clauses in a preprocessor.
+  fn subsume(&mut self, pre: &mut Preprocessor) {
+    let (subsumed, strengthened) = pre.subsume();
+    self.stats.record_subsumed_clauses(subsumed as u32);
+    self.stats.record_strengthened_clauses(strengthened as u32);
+  }
+  /// Moves every clause out of the watch list into a preprocessor.
+  /// Must be followed by `load_preprocessed`.
+  fn preprocessor(&mut self) -> Preprocessor {
+    debug_assert_eq!(self.level, 0);
+    let mut pre = Preprocessor::new(&self.assignments);
+    let crefs = self.watch_list.drain().filter_map(|(l_0, l_1, cref)| {
+      debug_assert_ne!(l_0, l_1);
+      if l_0 < l_1 {
+        Some(cref)
+      } else {
+        None
+      }
+    });
+    for cref in crefs {
+      pre.add_clause(cref.as_slice(&self.database), cref.is_learnt());
+    }
+    pre
+  }
+  /// Replaces every clause with the clauses of a preprocessor, and propagates its units.
+  /// Returns false if there is a conflict.
+  fn load_preprocessed(&mut self, pre: &Preprocessor) -> bool {
+    let max_var = self.database.max_var;
+    self.database.clear();
+    self.database.max_var = max_var;
+    self.vivified.clear();
+    self.watch_list.clear();
+    self.watch_list.resize(self.num_vars());
+    if pre.is_unsat() {
+      return false;
+    }
+    for var in 0..self.num_vars() {
+      if pre.is_eliminated(var) {
+        self.decision_vars[var as usize] = false;
+        self.eliminated[var as usize] = true;
+      }
+    }
+    self.unit_buf.clear();
+    for &lit in pre.units() {
+      let cref = self.database.add_clause_from_slice(&[lit]);
+      self.unit_buf.push((cref, lit));
+    }
+    for (clause, learnt) in pre.clauses() {
+      let cref = if learnt {
+        self.database.add_learnt_from_slice(clause)
+      } else {
+        self.database.add_clause_from_slice(clause)
+      };
+      let unit = self.watch_list.watch(cref, &self.database);
+      debug_assert!(unit.is_none());
+    }
+    self.with_units_from_buf().is_none()
+  }
+  /// Copies the assignments into the model, extended to variables removed by preprocessing.
+  fn extend_model(&mut self) {
+    self.model.clear();
+    self.model.extend_from_slice(&self.assignments);
+    self.reconstruction.extend(&mut self.model);
+  }
 
-  /// gets the final assignments for this solver
-  /// panics if any variable is still null.
+  /// Returns whether a restart must go back to level 0, because the clause database needs to
+  /// be reduced, or the search mode or phases need to be reset.
+  fn needs_full_restart(&self, max_learnts: f32) -> bool {
+    let conflicts = self.stats.clauses_learned as u64;
+    let ticks = self.stats.ticks;
+    self.stats.clauses_learned > (max_learnts as usize)
+      || (self.inactive_state.is_some() && self.mode_state.switch_suggested(conflicts, ticks))
+      || (self.options.rephase && self.phases.rephase_suggested(conflicts))
+  }
+  /// Returns the level a restart can backtrack to while keeping the same decisions.
+  /// Levels are kept as long as their decision has higher activity than the next decision
+  /// would, since restarting would pick them again anyways.
+  /// Assumptions are always kept, since they would be picked again first.
+  fn reuse_level(&mut self) -> u32 {
+    let next = loop {
+      let var = match self.var_state.peek_highest_prio() {
+        None => return self.level,
+        Some(var) => var,
+      };
+      if self.decision_vars[var as usize] && self.assignments[var as usize].is_none() {
+        break var;
+      }
+      // assigned variables would be skipped by choose_lit anyways
+      self.var_state.take_highest_prio();
+    };
+    let next_activity = self.var_state.activity(next);
+    let mut lvl = self.level.min(self.assumptions.len() as u32);
+    while lvl < self.level {
+      let decision = self.assignment_trail[self.level_indeces[lvl as usize] as usize];
+      if self.var_state.activity(decision.var()) <= next_activity {
+        break;
+      }
+      lvl += 1;
+    }
+    lvl
+  }
+  /// Switches between focused and stable search if the current phase is out of budget.
+  /// Must be called at level 0.
+  fn maybe_switch_mode(&mut self) {
+    debug_assert_eq!(self.level, 0);
+    let conflicts = self.stats.clauses_learned as u64;
+    let ticks = self.stats.ticks;
+    if !self.mode_state.switch_suggested(conflicts, ticks) {
+      return;
+    }
+    self.stats.record_mode_switch();
+    let inactive = self.inactive_state.as_mut().unwrap();
+    std::mem::swap(&mut self.var_state, inactive);
+    self.restart_state.policy = match self.mode_state.switch(conflicts, ticks) {
+      SearchMode::Focused => RestartPolicy::Ema,
+      SearchMode::Stable => RestartPolicy::Luby,
+    };
+  }
+  /// Returns the highest level of any literal in a false clause.
+  /// If only one literal is at that level, it is also returned, as the clause should have
+  /// implied it.
+  fn conflict_level(&self, cref: &CRef) -> (u32, Option<Literal>) {
+    let mut max = 0;
+    let mut at_max = None;
+    let mut count = 0;
+    for &lit in cref.iter(&self.database) {
+      let lvl = self.levels[lit.var() as usize];
+      if lvl > max {
+        max = lvl;
+        at_max = Some(lit);
+        count = 1;
+      } else if lvl == max {
+        count += 1;
+      }
+    }
+    (max, if count == 1 { at_max } else { None })
+  }
+  /// Returns the number of distinct levels in a clause whose literals are all assigned.
+  fn lbd(&mut self, cref: &CRef) -> u32 {
+    let levels = &self.levels;
+    self.level_buf.clear();
+    self.level_buf.extend(
+      cref
+        .iter(&self.database)
+        .map(|lit| levels[lit.var() as usize]),
+    );
+    self.level_buf.sort_unstable();
+    self.level_buf.dedup();
+    self.level_buf.len() as u32
+  }
+
+  /// gets the final assignments for this solver, after solve has found a solution
+  /// panics if any decision variable is still null.
+  /// Non-decision variables may be left unassigned if nothing implied them.
   pub fn final_assignments(&self) -> &[Option<bool>] {
     assert!(
-      !self.has_unassigned_vars(),
+      self.model.len() == self.assignments.len()
+        && self
+          .model
+          .iter()
+        .zip(self.decision_vars.iter())
+        .all(|(assn, &decision)| assn.is_some() || !decision),
       "There is no final assignment while there are unassigned variables"
     );
-    &self.assignments
+    &self.model
+  }
+  /// Suggests that var should be assigned to phase when it is picked as a decision.
+  /// This overrides the phase policy in the solver's options.
+  pub fn set_phase(&mut self, var: u32, phase: bool) { self.phases.force(var, Some(phase)); }
+  /// Removes a phase suggested by `set_phase`.
+  pub fn clear_phase(&mut self, var: u32) { self.phases.force(var, None); }
+  /// Sets the score of var in the decision heuristic, where higher scores are picked first.
+  /// Later conflicts will continue to update the score.
+  pub fn set_decision_priority(&mut self, var: u32, priority: f32) {
+    self.var_state.set_activity(var, priority);
+    if let Some(inactive) = self.inactive_state.as_mut() {
+      inactive.set_activity(var, priority);
+    }
+  }
+  /// Sets whether var can be picked as a decision. Variables which are not decisions are only
+  /// assigned by propagation, so they should be determined by the decision variables, such as
+  /// auxiliary variables of an encoding.
+  pub fn set_decision_var(&mut self, var: u32, decision: bool) {
+    let was_decision = replace(&mut self.decision_vars[var as usize], decision);
+    if decision && !was_decision && self.assignments[var as usize].is_none() {
+      self.var_state.enable(var);
+      if let Some(inactive) = self.inactive_state.as_mut() {
+        inactive.enable(var);
+      }
+    }
   }
+  /// Sets whether var must be kept by preprocessing, which is needed for variables in
+  /// assumptions or in clauses added after preprocessing.
+  pub fn set_frozen(&mut self, var: u32, frozen: bool) { self.frozen[var as usize] = frozen; }
+  /// Adds a variable to this solver, and returns it.
+  pub fn new_var(&mut self) -> u32 {
+    let var = self.num_vars();
+    self.resize(var + 1);
+    self.database.max_var = self.database.max_var.max(var + 1);
+    var
+  }
+  /// Returns the number of variables in this solver
+  pub fn num_vars(&self) -> u32 { self.assignments.len() as u32 }
   /// Are still unassigned variables for this solver?
   pub fn has_unassigned_vars(&self) -> bool { self.assignment_trail.len() < self.assignments.len() }
   /// returns the reason for a var's assignment if it exists
@@ -227,7 +1099,11 @@ impl Solver {
           })
           .count();
         let mut idx = trail_idx;
-        while !seen.contains_key(&trail[idx].var()) && idx > 0 {
+        // lower levels may be interleaved on the trail after chronological backtracking
+        while idx > 0
+          && !(levels[trail[idx].var() as usize] >= decision_level
+            && seen.contains_key(&trail[idx].var()))
+        {
           idx -= 1;
         }
         let lit_on_path = trail[idx];
@@ -245,20 +1121,66 @@ impl Solver {
     }
     let mut seen_stack = replace(&mut self.seen_stack, vec![]);
     // minimization before adding asserting literal
+    let before = learnt.len();
     learnt.retain(|lit| {
-      self.reason(lit.var()).is_none() || !self.lit_redundant(*lit, &mut seen, &mut seen_stack)
+      let redundant =
+        self.reason(lit.var()).is_some() && self.lit_redundant(*lit, &mut seen, &mut seen_stack);
+      // afterwards, only literals in the clause are marked as sources
+      if redundant {
+        seen.insert(lit.var(), SeenState::Redundant);
+      }
+      !redundant
     });
     self.seen_stack = seen_stack;
+    self
+      .stats
+      .record_minimized_literals((before - learnt.len()) as u32);
+
+    if self.options.shrink {
+      let mut open = replace(
+        &mut self.shrink_open,
+        HashSet::with_hasher(Default::default()),
+      );
+      let shrunk = self.shrink(&mut learnt, &mut seen, &mut open);
+      self.shrink_open = open;
+      self.stats.record_shrunk_literals(shrunk as u32);
+    }
 
     // add asserting literal
     learnt.push(!causes.3);
+    seen.insert(causes.3.var(), SeenState::Source);
+    if self.options.minimize_binary {
+      let removed = self.minimize_binary(&mut learnt, &mut seen);
+      self.stats.record_binary_minimized_literals(removed as u32);
+    }
+    // variables which implied the learnt clause but are not in it
+    if self.var_state.uses_reasons() {
+      for lit in learnt.iter() {
+        let reason = match self.causes[lit.var() as usize] {
+          None => continue,
+          Some(reason) => reason,
+        };
+        for l in reason.iter(&self.database) {
+          if self.levels[l.var() as usize] == 0 {
+            continue;
+          }
+          match seen.get(&l.var()) {
+            Some(SeenState::Source) | Some(SeenState::Reasoned) => continue,
+            _ => {
+              seen.insert(l.var(), SeenState::Reasoned);
+              self.var_state.reasoned(l.var());
+            },
+          }
+        }
+      }
+    }
     seen.clear();
     self.analyze_seen = seen;
 
     if learnt.len() == 1 {
       // backtrack to 0
       self.learnt_buf = learnt;
-      let cref = self.database.add_clause_from_slice(&self.learnt_buf);
+      let cref = self.database.add_learnt_from_slice(&self.learnt_buf);
       self.learnt_buf.clear();
       return (cref, 0);
     }
@@ -269,7 +1191,7 @@ impl Solver {
     let (max, second) = match others.next() {
       None => {
         self.learnt_buf = learnt;
-        let cref = self.database.add_clause_from_slice(&self.learnt_buf);
+        let cref = self.database.add_learnt_from_slice(&self.learnt_buf);
         self.learnt_buf.clear();
         return (cref, curr_max);
       },
@@ -285,11 +1207,48 @@ impl Solver {
       Ordering::Less => (max, second.max(next)),
     });
     self.learnt_buf = learnt;
-    let cref = self.database.add_clause_from_slice(&self.learnt_buf);
+    let cref = self.database.add_learnt_from_slice(&self.learnt_buf);
     self.learnt_buf.clear();
     (cref, second)
   }
 
+  /// Finds which assumptions imply that the assumption lit is false, by walking back from it
+  /// along the reasons of each literal to the decided assumptions.
+  fn analyze_final(&mut self, lit: Literal) {
+    self.failed_assumptions.clear();
+    self.failed_assumptions.push(lit);
+    if self.levels[lit.var() as usize] == 0 {
+      return;
+    }
+    let mut seen = replace(
+      &mut self.analyze_seen,
+      HashMap::with_hasher(Default::default()),
+    );
+    debug_assert!(seen.is_empty());
+    seen.insert(lit.var(), SeenState::Source);
+    for &l in self.assignment_trail.iter().rev() {
+      if seen.remove(&l.var()).is_none() {
+        continue;
+      }
+      match self.causes[l.var() as usize] {
+        // only assumptions are decided below the current level
+        None => self.failed_assumptions.push(l),
+        Some(reason) => {
+          for r in reason.iter(&self.database) {
+            if r.var() != l.var() && self.levels[r.var() as usize] > 0 {
+              seen.insert(r.var(), SeenState::Source);
+            }
+          }
+        },
+      }
+      if seen.is_empty() {
+        break;
+      }
+    }
+    seen.clear();
+    self.analyze_seen = seen;
+  }
+
   /// Increases the level on this solver and returns the level assigned.
   pub fn next_level(&mut self) -> u32 {
     self.level_indeces.push(self.assignment_trail.len() as u32);
@@ -298,18 +1257,24 @@ impl Solver {
   }
   /// revert to given level, retaining all state at that level.
   fn backtrack_to(&mut self, lvl: u32) {
-    debug_assert!(lvl >= self.level);
-    /*
     if lvl >= self.level {
-      // TODO I'm not sure if this ever actually occurs
       return;
     }
-    */
     self.level = lvl;
     let index = self.level_indeces[lvl as usize] as usize;
     self.level_indeces.truncate(lvl as usize);
-    for lit in self.assignment_trail.drain(index..) {
+    // After chronological backtracking, literals from lower levels may be after index,
+    // so they are moved down instead of being unassigned.
+    let mut kept = index;
+    for i in index..self.assignment_trail.len() {
+      let lit = self.assignment_trail[i];
       let var = lit.var();
+      if self.levels[var as usize] <= lvl {
+        self.assignment_trail[kept] = lit;
+        self.kept_buf.push(lit);
+        kept += 1;
+        continue;
+      }
       let prev_assn = self.assignments[var as usize].take();
       debug_assert_ne!(prev_assn, None);
 
@@ -318,14 +1283,17 @@ impl Solver {
 
       self.polarities[var as usize] = lit.val();
       self.causes[var as usize] = None;
-      self.var_state.enable(var);
+      if self.decision_vars[var as usize] {
+        self.var_state.enable(var);
+      }
     }
+    self.assignment_trail.truncate(kept);
     debug_assert_eq!(self.level_indeces.len(), lvl as usize);
   }
   /// Records a literal written at the current level, with a possible cause
   fn with(&mut self, lit: Literal, cause: Option<CRef>) -> Option<CRef> {
+    self.queue_kept();
     let units = &mut self.unit_buf;
-    units.clear();
     match cause {
       // In the case there was no previous cause, we need to do one iteration
       None => {
@@ -336,6 +1304,8 @@ impl Solver {
 
         debug_assert_eq!(self.assignments[lit.var() as usize], None);
         self.assignments[lit.var() as usize] = Some(lit.val());
+        self.var_state.assigned(lit.var());
+        self.stats.record_ticks(self.watch_list.num_watches(!lit));
         self
           .watch_list
           .set(lit, &self.assignments, &self.database, |c, l| {
@@ -347,6 +1317,30 @@ impl Solver {
     self.with_units_from_buf()
   }
 
+  /// Propagates literals kept out of order by the last backtrack.
+  fn propagate_kept(&mut self) -> Option<CRef> {
+    self.queue_kept();
+    self.with_units_from_buf()
+  }
+  /// Clears unit_buf, then revisits the watches of literals kept out of order by the last
+  /// backtrack. Clauses they watch may have become unit or false at a level which was undone
+  /// before those units were assigned.
+  fn queue_kept(&mut self) {
+    let units = &mut self.unit_buf;
+    units.clear();
+    for lit in self.kept_buf.drain(..) {
+      // a later backtrack may have undone this literal
+      if lit.assn(&self.assignments) != Some(true) {
+        continue;
+      }
+      self.stats.record_ticks(self.watch_list.num_watches(!lit));
+      self
+        .watch_list
+        .set(lit, &self.assignments, &self.database, |c, l| {
+          units.push((c, l))
+        });
+    }
+  }
   /// reads from unit_buf and attempts to propogate from there
   fn with_units_from_buf(&mut self) -> Option<CRef> {
     let units = &mut self.unit_buf;
@@ -362,9 +1356,15 @@ impl Solver {
       let prev_cause = self.causes[var].replace(cause);
       debug_assert_eq!(prev_cause, None);
       debug_assert_eq!(self.levels[var], INVALID_LEVEL);
-      self.levels[var] = self.level;
+      self.levels[var] = match self.options.chrono_threshold {
+        // literals are always implied at the current level without chronological backtracking
+        None => self.level,
+        Some(_) => implied_level(&cause, lit, &self.database, &self.levels),
+      };
       let prev_assn = self.assignments[var].replace(lit.val());
       debug_assert_eq!(prev_assn, None);
+      self.var_state.assigned(lit.var());
+      self.stats.record_ticks(self.watch_list.num_watches(!lit));
       self
         .watch_list
         .set(lit, &self.assignments, &self.database, |c, l| {
@@ -373,16 +1373,53 @@ impl Solver {
     }
     None
   }
-  /// Chooese the next decision literal.
-  fn choose_lit(&mut self) -> Literal {
+  /// Chooese the next decision literal, or None if all decision variables are assigned.
+  fn choose_lit(&mut self) -> Option<Literal> {
     debug_assert!(self.has_unassigned_vars());
+    if let Some(var) = self.choose_projected() {
+      return Some(self.decision(var));
+    }
+    if self.options.random_decision_freq > 0.0
+      && self.rng.next_f32() < self.options.random_decision_freq
+    {
+      let var = self.rng.below(self.num_vars());
+      if self.decision_vars[var as usize] && self.assignments[var as usize].is_none() {
+        self.stats.record_random_decision();
+        return Some(self.decision(var));
+      }
+    }
     loop {
-      let var = self.var_state.take_highest_prio();
+      let var = self.var_state.take_highest_prio()?;
+      // non-decision variables are left taken until they are made decisions again
+      if !self.decision_vars[var as usize] {
+        continue;
+      }
       if self.assignments[var as usize].is_none() {
-        return Literal::new(var as u32, !self.polarities[var as usize]);
+        return Some(self.decision(var));
       }
     }
   }
+  /// Returns the unassigned projected variable with the highest score, if there is one.
+  fn choose_projected(&self) -> Option<u32> {
+    let var_state = &self.var_state;
+    self
+      .projection
+      .as_ref()?
+      .iter()
+      .copied()
+      .filter(|&var| self.decision_vars[var as usize] && self.assignments[var as usize].is_none())
+      .max_by(|&a, &b| {
+        let (a, b) = (var_state.activity(a), var_state.activity(b));
+        a.partial_cmp(&b).unwrap()
+      })
+  }
+  /// Returns the literal assigning var to the phase it should be decided with
+  fn decision(&mut self, var: u32) -> Literal {
+    let phase = self
+      .phases
+      .phase(self.options.phase, var, &self.polarities, &mut self.rng);
+    Literal::new(var, !phase)
+  }
 
   fn lit_redundant(
     &self,
@@ -430,22 +1467,220 @@ impl Solver {
     true
   }
 
+  /// Replaces each block of literals from the same level in a learnt clause with the unique
+  /// implication point of that block, as in CaDiCaL's shrinking. The learnt clause must not
+  /// contain the asserting literal yet. Returns the number of literals removed.
+  fn shrink(
+    &self,
+    learnt: &mut Vec<Literal>,
+    seen: &mut HashMap<u32, SeenState, BuildHasherDefault<FxHasher>>,
+    open: &mut HashSet<u32, BuildHasherDefault<FxHasher>>,
+  ) -> usize {
+    let levels = &self.levels;
+    learnt.sort_unstable_by_key(|lit| std::cmp::Reverse(levels[lit.var() as usize]));
+    let mut removed = 0;
+    let mut start = 0;
+    while start < learnt.len() {
+      let lvl = levels[learnt[start].var() as usize];
+      let end = start
+        + learnt[start..]
+          .iter()
+          .take_while(|lit| levels[lit.var() as usize] == lvl)
+          .count();
+      if end - start > 1 {
+        if let Some(uip) = self.block_uip(&learnt[start..end], lvl, seen, open) {
+          for lit in learnt.drain(start..end) {
+            seen.insert(lit.var(), SeenState::Redundant);
+          }
+          seen.insert(uip.var(), SeenState::Source);
+          learnt.insert(start, !uip);
+          removed += end - start - 1;
+          start += 1;
+          continue;
+        }
+      }
+      start = end;
+    }
+    removed
+  }
+  /// Finds the literal on the trail which implies every literal in this block on its own.
+  /// Fails if resolving the block depends on any literal from a lower level which is not already
+  /// in the learnt clause.
+  fn block_uip(
+    &self,
+    block: &[Literal],
+    lvl: u32,
+    seen: &HashMap<u32, SeenState, BuildHasherDefault<FxHasher>>,
+    open: &mut HashSet<u32, BuildHasherDefault<FxHasher>>,
+  ) -> Option<Literal> {
+    open.clear();
+    open.extend(block.iter().map(|lit| lit.var()));
+    let mut remaining = block.len();
+    // lower levels may be interleaved on the trail after chronological backtracking
+    let mut idx = match self.options.chrono_threshold {
+      None => self.level_indeces[lvl as usize] as usize,
+      Some(_) => self.assignment_trail.len(),
+    };
+    loop {
+      idx = idx.checked_sub(1)?;
+      let lit = self.assignment_trail[idx];
+      if self.levels[lit.var() as usize] != lvl || !open.contains(&lit.var()) {
+        continue;
+      }
+      if remaining == 1 {
+        return Some(lit);
+      }
+      remaining -= 1;
+      let reason = self.reason(lit.var())?;
+      for other in reason.iter(&self.database) {
+        if other.var() == lit.var() {
+          continue;
+        }
+        match self.levels[other.var() as usize] {
+          0 => (),
+          l if l == lvl => {
+            if open.insert(other.var()) {
+              remaining += 1;
+            }
+          },
+          _ => {
+            if seen.get(&other.var()) != Some(&SeenState::Source) {
+              return None;
+            }
+          },
+        }
+      }
+    }
+  }
+  /// Removes literals from a learnt clause which are implied by a binary clause with the
+  /// asserting literal, which must be last. Returns the number of literals removed.
+  fn minimize_binary(
+    &self,
+    learnt: &mut Vec<Literal>,
+    seen: &mut HashMap<u32, SeenState, BuildHasherDefault<FxHasher>>,
+  ) -> usize {
+    let asserting = *learnt.last().unwrap();
+    for (cref, other) in self.watch_list.watches(asserting) {
+      if cref.len() != 2 || other.assn(&self.assignments) != Some(true) {
+        continue;
+      }
+      // (asserting, other) resolves with !other in the learnt clause, removing it
+      if let Some(ss) = seen.get_mut(&other.var()) {
+        if *ss == SeenState::Source {
+          *ss = SeenState::Redundant;
+        }
+      }
+    }
+    let before = learnt.len();
+    learnt.retain(|lit| seen.get(&lit.var()) == Some(&SeenState::Source));
+    before - learnt.len()
+  }
+
   /// Loads a DIMACs file into this solver
   pub fn load_dimacs<S: AsRef<Path>>(&mut self, s: S) -> io::Result<bool> {
     crate::parser::from_dimacs(s, &mut self.database, &mut self.cref_buf)?;
     self.resize(self.database.max_var);
     let mut cref_buf = replace(&mut self.cref_buf, vec![]);
+    // watch every clause before propagating, otherwise later clauses may be watched on literals
+    // which were already falsified at level 0.
+    self.unit_buf.clear();
     for cref in cref_buf.drain(..) {
-      let lit = self.watch_list.watch(cref, &self.database);
-      if let Some(lit) = lit {
-        if let Some(_conflict) = self.with(lit, Some(cref)) {
-          // This show there is a conflict
-          return Ok(false);
-        }
+      if let Some(lit) = self.watch_list.watch(cref, &self.database) {
+        self.unit_buf.push((cref, lit));
       }
     }
     self.cref_buf = cref_buf;
-    Ok(true)
+    // This shows there is a conflict
+    let no_conflict = self.with_units_from_buf().is_none();
+    self.unsat |= !no_conflict;
+    Ok(no_conflict)
+  }
+  /// Adds a clause to this solver, which can be done between calls to solve.
+  /// If preprocessing has run, variables in the clause must have been frozen before it.
+  /// Returns false if the solver has no solution anymore.
+  pub fn add_clause(&mut self, lits: &[Literal]) -> bool {
+    if self.unsat {
+      return false;
+    }
+    self.backtrack_to(0);
+    if self.propagate_kept().is_some() {
+      self.unsat = true;
+      return false;
+    }
+    let max_var = lits.iter().map(|lit| lit.var() + 1).max().unwrap_or(0);
+    if max_var > self.num_vars() {
+      self.resize(max_var);
+      self.database.max_var = max_var;
+    }
+    let mut clause = take(&mut self.learnt_buf);
+    debug_assert!(clause.is_empty());
+    let mut satisfied = false;
+    for &lit in lits {
+      assert!(
+        !self.eliminated[lit.var() as usize],
+        "Variables in added clauses must be frozen before preprocessing"
+      );
+      match lit.assn(&self.assignments) {
+        Some(true) => satisfied = true,
+        Some(false) => continue,
+        None if clause.contains(&!lit) => satisfied = true,
+        None if clause.contains(&lit) => continue,
+        None => clause.push(lit),
+      }
+      if satisfied {
+        break;
+      }
+    }
+    let no_conflict = if satisfied {
+      true
+    } else {
+      match clause.len() {
+        0 => false,
+        1 => {
+          let cref = self.database.add_clause_from_slice(&clause);
+          self.with(clause[0], Some(cref)).is_none()
+        },
+        _ => {
+          let cref = self.database.add_clause_from_slice(&clause);
+          let unit = self.watch_list.watch(cref, &self.database);
+          debug_assert!(unit.is_none());
+          true
+        },
+      }
+    };
+    clause.clear();
+    self.learnt_buf = clause;
+    self.unsat |= !no_conflict;
+    no_conflict
+  }
+  /// Returns a new solver with the same options, variables and saved phases as this one, and
+  /// its units and clauses at level 0 without any learnt clauses. Constraints which are only
+  /// needed for a while can be added to the copy, and are gone once it is dropped.
+  pub fn fork(&self) -> Self {
+    let mut out = Self::with_options(self.options.clone());
+    out.resize(self.num_vars());
+    out.database.max_var = self.database.max_var;
+    out.frozen.copy_from_slice(&self.frozen);
+    out.eliminated.copy_from_slice(&self.eliminated);
+    out.polarities.copy_from_slice(&self.polarities);
+    out.reconstruction = self.reconstruction.clone();
+    out.preprocessed = self.preprocessed;
+    for var in 0..self.num_vars() {
+      if !self.decision_vars[var as usize] {
+        out.set_decision_var(var, false);
+      }
+    }
+    let levels = &self.levels;
+    for &lit in &self.assignment_trail {
+      if levels[lit.var() as usize] == 0 {
+        out.add_clause(&[lit]);
+      }
+    }
+    for cref in self.watch_list.clauses().filter(|cref| !cref.is_learnt()) {
+      out.add_clause(cref.as_slice(&self.database));
+    }
+    out.unsat |= self.unsat;
+    out
   }
 
   /// Resizes this solver to be ready to handle max_vars.
@@ -455,10 +1690,17 @@ impl Solver {
     self.assignments.resize(max_vars as usize, None);
     self.causes.resize(max_vars as usize, None);
     self.polarities.resize(max_vars as usize, false);
+    self.decision_vars.resize(max_vars as usize, true);
+    self.frozen.resize(max_vars as usize, false);
+    self.eliminated.resize(max_vars as usize, false);
+    self.phases.resize(max_vars);
 
     self.watch_list.resize(max_vars);
 
     self.var_state.resize(max_vars);
+    if let Some(inactive) = self.inactive_state.as_mut() {
+      inactive.resize(max_vars);
+    }
   }
 
   /// Clears all assignments from this solver
@@ -471,26 +1713,513 @@ impl Solver {
     self.watch_list.clear();
     self.database.clear();
     self.polarities.clear();
-    self.var_state.clear();
+    self.decision_vars.clear();
+    self.frozen.clear();
+    self.eliminated.clear();
+    self.unsat = false;
+    self.assumptions.clear();
+    self.failed_assumptions.clear();
+    self.projection = None;
+    self.reconstruction.clear();
+    self.preprocessed = false;
+    self.next_subsume = SUBSUME_INTERVAL;
+    self.next_vivify = VIVIFY_INTERVAL;
+    self.vivified_ticks = 0;
+    self.vivified.clear();
+    self.simplified_trail = 0;
+    self.model.clear();
+    self.phases = PhaseState::new(self.options.rephase_interval);
+    self.rng = Rng::new(self.options.seed);
+    let (var_state, inactive_state) = Self::heuristics(&self.options);
+    self.var_state = var_state;
+    self.inactive_state = inactive_state;
     self.level = 0;
-    self.restart_state = RestartState::new(RESTART_BASE, RESTART_INC);
+    self.restart_state = Self::restart_state(&self.options);
+    self.mode_state = ModeState::new(&self.options);
 
     self.analyze_seen.clear();
     self.stats = Stats::new();
     self.learnt_buf.clear();
+    self.level_buf.clear();
     self.unit_buf.clear();
+    self.kept_buf.clear();
     self.cref_buf.clear();
     self.seen_stack.clear();
+    self.probe_buf.clear();
+    self.shrink_open.clear();
+  }
+}
+
+/// Iterator over the solutions of a solver, created by `Solver::enumerate_models`.
+#[derive(Debug)]
+pub struct Models<'a> {
+  solver: &'a mut Solver,
+  assumptions: Vec<Literal>,
+  blocking: Vec<Literal>,
+  done: bool,
+}
+
+impl Iterator for Models<'_> {
+  type Item = Vec<Option<bool>>;
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.done || !self.solver.solve_with_assumptions(&self.assumptions) {
+      self.done = true;
+      return None;
+    }
+    let model = self.solver.final_assignments().to_vec();
+    self.blocking.clear();
+    self
+      .blocking
+      .extend(self.assumptions.iter().map(|&lit| !lit));
+    self.solver.blocking_clause(&mut self.blocking);
+    self.done = !self.solver.add_clause(&self.blocking);
+    Some(model)
   }
 }
 
+impl Drop for Models<'_> {
+  fn drop(&mut self) { self.solver.projection = None; }
+}
+
+/// Returns the level a literal implied by cause should be assigned at, which is the highest
+/// level of the other literals in the clause.
+fn implied_level(cause: &CRef, lit: Literal, db: &Database, levels: &[u32]) -> u32 {
+  cause
+    .iter(db)
+    .filter(|l| l.var() != lit.var())
+    .map(|l| levels[l.var() as usize])
+    .max()
+    .unwrap_or(0)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum SeenState {
   Source,
   Redundant,
   Required,
+  Reasoned,
 }
 
 impl Default for Solver {
   fn default() -> Self { Self::new() }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    test_util::{check_solver, dimacs_clauses, models, random_clauses, satisfies},
+    Phase,
+  };
+
+  fn clause(lits: &[i32]) -> Vec<Literal> { lits.iter().map(|&l| Literal::from(l)).collect() }
+
+  /// Returns a solver with these clauses, which decides the literals in order before any other
+  /// variable, as long as no conflict has changed their scores.
+  fn ordered_solver(options: Options, clauses: &[&[i32]], order: &[i32]) -> Solver {
+    let mut solver = Solver::with_options(options);
+    for lits in clauses {
+      solver.add_clause(&clause(lits));
+    }
+    for (i, &lit) in order.iter().enumerate() {
+      let lit = Literal::from(lit);
+      solver.set_decision_priority(lit.var(), (order.len() - i) as f32);
+      solver.set_phase(lit.var(), lit.val());
+    }
+    solver
+  }
+
+  #[test]
+  pub fn test_chrono_out_of_order() {
+    let mut options = Options::new();
+    options.chrono_threshold = Some(0);
+    // deciding 3 after 1 and 2 learns (-1 -3), which jumps back over the level of 2
+    let clauses: &[&[i32]] = &[&[-1, -3, 4], &[-1, -3, -4]];
+    let mut solver = ordered_solver(options, clauses, &[2, 3]);
+    assert!(solver.solve_with_assumptions(&clause(&[1])));
+    assert_eq!(solver.stats.chrono_backtracks, 1);
+    // -3 is implied at the level of 1 while 2 is still assigned above it
+    let (two, three) = (Literal::from(2), Literal::from(-3));
+    assert_eq!(solver.levels[two.var() as usize], 2);
+    assert_eq!(solver.levels[three.var() as usize], 1);
+    let position = |lit| solver.assignment_trail.iter().position(|&l| l == lit);
+    assert!(position(two) < position(three));
+
+    // backtracking keeps -3 and moves it down the trail
+    solver.backtrack_to(1);
+    assert_eq!(solver.assignment_trail, clause(&[1, -3]));
+    assert_eq!(three.assn(&solver.assignments), Some(true));
+    assert_eq!(two.assn(&solver.assignments), None);
+    assert_eq!(solver.kept_buf, [three]);
+
+    assert!(!solver.solve_with_assumptions(&clause(&[1, 3])));
+    let mut failed = solver.failed_assumptions().to_vec();
+    failed.sort();
+    assert_eq!(failed, clause(&[1, 3]));
+  }
+
+  #[test]
+  pub fn test_enumerate_models() {
+    let mut rng = Rng::new(1);
+    for i in 0..200 {
+      let vars = 1 + i % 8;
+      let num_clauses = rng.below(4 * vars) as usize;
+      let clauses = random_clauses(&mut rng, vars, num_clauses, 3);
+      let mut expected = models(vars, &clauses);
+      expected.sort();
+      let mut solver = Solver::new();
+      solver.resize(vars);
+      for lits in &clauses {
+        solver.add_clause(lits);
+      }
+      // blocking clauses stay after the first iterator is dropped, so the second one only finds
+      // the remaining models
+      let mut found = solver
+        .enumerate_models(None)
+        .take(expected.len() / 2)
+        .collect::<Vec<_>>();
+      found.extend(solver.enumerate_models(None));
+      found.sort();
+      assert_eq!(found, expected, "{:?}", clauses);
+      assert!(!solver.solve());
+    }
+  }
+
+  #[test]
+  pub fn test_enumerate_projected_models() {
+    let mut rng = Rng::new(2);
+    for i in 0..200 {
+      let vars = 1 + i % 8;
+      let num_clauses = rng.below(4 * vars) as usize;
+      let clauses = random_clauses(&mut rng, vars, num_clauses, 3);
+      let projection = (0..vars).step_by(2).collect::<Vec<_>>();
+      let project = |model: &[Option<bool>]| {
+        projection
+          .iter()
+          .map(|&var| model[var as usize])
+          .collect::<Vec<_>>()
+      };
+      let mut expected = models(vars, &clauses)
+        .iter()
+        .map(|model| project(model))
+        .collect::<Vec<_>>();
+      expected.sort();
+      expected.dedup();
+      let mut solver = Solver::new();
+      solver.resize(vars);
+      for lits in &clauses {
+        solver.add_clause(lits);
+      }
+      let mut found = vec![];
+      for model in solver.enumerate_models(Some(&projection)) {
+        assert!(satisfies(&model, &clauses), "{:?}", clauses);
+        found.push(project(&model));
+      }
+      // each projection is found exactly once
+      found.sort();
+      assert_eq!(found, expected, "{:?}", clauses);
+    }
+  }
+
+  #[test]
+  pub fn test_non_decision_var() {
+    // 3 is true exactly when 1 and 2 are, and would be decided first if it could be
+    let clauses: &[&[i32]] = &[&[-3, 1], &[-3, 2], &[3, -1, -2]];
+    let mut solver = ordered_solver(Options::new(), clauses, &[3]);
+    solver.set_decision_var(2, false);
+    for assumptions in &[&[][..], &[1, 2], &[1, -2], &[-1, 2], &[-1, -2]] {
+      assert!(solver.solve_with_assumptions(&clause(assumptions)));
+      assert!(solver.reason(2).is_some());
+      let model = solver.final_assignments();
+      assert_eq!(
+        model[2],
+        Some(model[0] == Some(true) && model[1] == Some(true))
+      );
+    }
+  }
+
+  #[test]
+  pub fn test_set_phase() {
+    for &phase in &[Phase::False, Phase::True, Phase::Random] {
+      let mut options = Options::new();
+      options.phase = phase;
+      let mut solver = Solver::with_options(options);
+      solver.resize(6);
+      for var in 0..6 {
+        solver.set_phase(var, var % 3 == 0);
+      }
+      assert!(solver.solve());
+      let expected = (0..6).map(|var| Some(var % 3 == 0)).collect::<Vec<_>>();
+      assert_eq!(solver.final_assignments(), &expected[..]);
+    }
+  }
+
+  #[test]
+  pub fn test_decision_priority() {
+    let mut solver = Solver::new();
+    solver.resize(5);
+    for (var, &priority) in [2.0, 5.0, 1.0, 4.0, 3.0].iter().enumerate() {
+      solver.set_decision_priority(var as u32, priority);
+    }
+    assert!(solver.solve());
+    // without any conflicts, each variable is decided in order of priority
+    assert_eq!(solver.levels, [4, 1, 5, 2, 3]);
+  }
+
+  #[test]
+  pub fn test_shrink() {
+    // deciding 4 after 1 learns (-2 -3 -4), where 1 implies both 2 and 3
+    let clauses: &[&[i32]] = &[&[-1, 2], &[-1, 3], &[-2, -3, -4, 5], &[-2, -3, -4, -5]];
+    for &shrink in &[false, true] {
+      let mut options = Options::new();
+      options.shrink = shrink;
+      let mut solver = ordered_solver(options, clauses, &[1, 4]);
+      assert!(solver.solve());
+      assert_eq!(solver.stats.clauses_learned, 1);
+      assert_eq!(solver.stats.shrunk_literals, shrink as u32);
+      assert_eq!(solver.stats.learnt_literals, 3 - shrink as u32);
+    }
+  }
+
+  #[test]
+  pub fn test_minimize_binary() {
+    // deciding 2 after 1 learns (-1 -2), where (1 -2) removes -1
+    let clauses: &[&[i32]] = &[&[-1, -2, 3], &[-1, -2, -3], &[1, -2]];
+    for &minimize_binary in &[false, true] {
+      let mut options = Options::new();
+      options.minimize_binary = minimize_binary;
+      let mut solver = ordered_solver(options, clauses, &[1, 2]);
+      assert!(solver.solve());
+      assert_eq!(solver.stats.clauses_learned, 1);
+      let removed = minimize_binary as u32;
+      assert_eq!(solver.stats.binary_minimized_literals, removed);
+      assert_eq!(solver.stats.learnt_literals, 2 - removed);
+    }
+  }
+
+  #[test]
+  pub fn test_solves_while_minimizing() {
+    for &(shrink, minimize_binary) in &[(true, false), (false, true), (true, true)] {
+      let mut options = Options::new();
+      options.shrink = shrink;
+      options.minimize_binary = minimize_binary;
+      check_solver(&options, 1);
+    }
+  }
+
+  /// Returns random clauses with three distinct variables each.
+  fn random_3sat(rng: &mut Rng, vars: u32, clauses: usize) -> Vec<Vec<Literal>> {
+    (0..clauses)
+      .map(|_| {
+        let mut lits = vec![];
+        while lits.len() < 3 {
+          let lit = Literal::new(rng.below(vars), rng.next_bool());
+          if lits.iter().all(|l: &Literal| l.var() != lit.var()) {
+            lits.push(lit);
+          }
+        }
+        lits
+      })
+      .collect()
+  }
+
+  #[test]
+  pub fn test_same_seed_same_run() {
+    let clauses = random_3sat(&mut Rng::new(2), 100, 400);
+    let solve = || {
+      let mut options = Options::new();
+      options.seed = 7;
+      options.random_decision_freq = 0.1;
+      options.random_init_activity = true;
+      let mut solver = Solver::with_options(options);
+      for lits in &clauses {
+        solver.add_clause(lits);
+      }
+      assert!(solver.solve());
+      (solver.final_assignments().to_vec(), solver.stats)
+    };
+    let (model, stats) = solve();
+    let (other_model, mut other_stats) = solve();
+    assert!(stats.random_decisions > 0 && stats.clauses_learned > 0);
+    assert_eq!(model, other_model);
+    other_stats.start_time = stats.start_time;
+    assert_eq!(stats, other_stats);
+  }
+
+  #[test]
+  pub fn test_reuse_trail() {
+    // satisfiable, but with enough conflicts to restart a few times
+    let clauses = random_3sat(&mut Rng::new(1), 100, 400);
+    let assumptions = clause(&[1, -2]);
+    let solve = |reuse_trail| {
+      let mut options = Options::new();
+      options.reuse_trail = reuse_trail;
+      let mut solver = Solver::with_options(options);
+      for lits in &clauses {
+        solver.add_clause(lits);
+      }
+      let sat = solver.solve_with_assumptions(&assumptions);
+      if sat {
+        assert!(satisfies(solver.final_assignments(), &clauses));
+      }
+      (sat, solver.stats)
+    };
+    let (sat, _) = solve(false);
+    let (reused_sat, stats) = solve(true);
+    assert!(sat && reused_sat);
+    assert!(stats.reused_trails > 0);
+  }
+
+  #[test]
+  pub fn test_solves_reusing_trail() {
+    let mut options = Options::new();
+    options.reuse_trail = true;
+    check_solver(&options, 1);
+  }
+
+  #[test]
+  pub fn test_dump_dimacs() {
+    let mut rng = Rng::new(3);
+    let mut dumped_learnts = false;
+    for i in 0..100 {
+      let vars = 8 + i % 6;
+      let mut clauses = random_3sat(&mut rng, vars, 4 * vars as usize);
+      // units are fixed at level 0, and the clauses they satisfy are left out
+      for _ in 0..rng.below(3) {
+        let unit = Literal::new(rng.below(vars), rng.next_bool());
+        let satisfied = Literal::new((unit.var() + 1) % vars, rng.next_bool());
+        clauses.push(vec![unit]);
+        clauses.push(vec![satisfied, unit]);
+      }
+      let mut solver = Solver::new();
+      solver.resize(vars);
+      for lits in &clauses {
+        solver.add_clause(lits);
+      }
+      solver.solve();
+      let expected = models(vars, &clauses);
+      let mut lines = vec![];
+      for &include_learnts in &[false, true] {
+        let mut out = vec![];
+        solver.dump_dimacs(&mut out, include_learnts).unwrap();
+        let header = String::from_utf8_lossy(&out)
+          .lines()
+          .next()
+          .unwrap()
+          .to_string();
+        let dumped = dimacs_clauses(&out);
+        assert_eq!(header, format!("p cnf {} {}", vars, dumped.len()));
+        assert_eq!(models(vars, &dumped), expected, "{:?}", clauses);
+        lines.push(dumped.len());
+      }
+      dumped_learnts |= lines[1] > lines[0];
+    }
+    assert!(dumped_learnts);
+  }
+
+  #[test]
+  pub fn test_write_simplified() {
+    let mut rng = Rng::new(1);
+    for i in 0..300 {
+      let vars = 1 + i % 10;
+      let num_clauses = rng.below(5 * vars) as usize;
+      let clauses = random_clauses(&mut rng, vars, num_clauses, 3);
+      let mut options = Options::new();
+      options.elim = true;
+      options.subsume = true;
+      options.probe = true;
+      options.substitute = true;
+      options.block = true;
+      let mut solver = Solver::with_options(options);
+      solver.resize(vars);
+      for lits in &clauses {
+        solver.add_clause(lits);
+      }
+      let (mut cnf, mut map) = (vec![], vec![]);
+      let sat = solver.write_simplified(&mut cnf, &mut map).unwrap();
+      let expected = models(vars, &clauses);
+      if !sat {
+        assert!(expected.is_empty(), "{:?}", clauses);
+        continue;
+      }
+      let header = String::from_utf8_lossy(&cnf)
+        .lines()
+        .next()
+        .unwrap()
+        .to_string();
+      let simplified = dimacs_clauses(&cnf);
+      assert_eq!(
+        header,
+        format!("p cnf {} {}", vars, simplified.len()),
+        "{:?}",
+        clauses
+      );
+      let mut simplified_solver = Solver::new();
+      simplified_solver.resize(vars);
+      for lits in &simplified {
+        simplified_solver.add_clause(lits);
+      }
+      assert_eq!(
+        simplified_solver.solve(),
+        !expected.is_empty(),
+        "{:?}",
+        clauses
+      );
+      if expected.is_empty() {
+        continue;
+      }
+      let mut model = simplified_solver.final_assignments().to_vec();
+      // each line of the map is a witness followed by the clause it satisfies
+      for line in String::from_utf8(map).unwrap().lines() {
+        let lits = clause(
+          &line
+            .split_whitespace()
+            .map(|v| v.parse().unwrap())
+            .filter(|&v| v != 0)
+            .collect::<Vec<_>>(),
+        );
+        let (witness, removed) = (lits[0], &lits[1..]);
+        if removed.iter().all(|lit| lit.assn(&model) != Some(true)) {
+          model[witness.var() as usize] = Some(witness.val());
+        }
+      }
+      assert!(satisfies(&model, &clauses), "{:?}", clauses);
+    }
+  }
+
+  #[test]
+  pub fn test_vivify() {
+    // deciding -1 implies -2, so (1 2 3) only needs 1 and 3
+    let clauses: &[&[i32]] = &[&[1, 2, 3], &[1, -2], &[-3, 4, 5]];
+    let mut solver = ordered_solver(Options::new(), clauses, &[]);
+    assert!(solver.vivify());
+    assert_eq!(solver.stats.vivified_clauses, 1);
+    assert_eq!(solver.stats.vivified_literals, 1);
+    let mut out = vec![];
+    solver.dump_dimacs(&mut out, false).unwrap();
+    let mut dumped = dimacs_clauses(&out);
+    dumped.sort();
+    let mut expected = [clause(&[1, 3]), clause(&[1, -2]), clause(&[-3, 4, 5])];
+    expected.sort();
+    assert_eq!(dumped, expected);
+    // the replaced clause is dropped once the database is compacted
+    assert!(solver.compact());
+    assert_eq!(solver.database.literals.len(), 7);
+  }
+
+  #[test]
+  pub fn test_solves_with_vivify() {
+    let mut options = Options::new();
+    options.vivify = true;
+    check_solver(&options, 1);
+  }
+
+  #[test]
+  pub fn test_solves_with_chrono() {
+    for &threshold in &[0, 2] {
+      let mut options = Options::new();
+      options.chrono_threshold = Some(threshold);
+      check_solver(&options, threshold as u64 + 1);
+    }
+  }
+}
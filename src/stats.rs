@@ -4,13 +4,49 @@ use std::time::{Duration, Instant};
 pub struct Stats {
   /// how many restarts did this solver perform
   pub restarts: u32,
+  /// how many times did this solver backtrack chronologically instead of backjumping
+  pub chrono_backtracks: u32,
+  /// how many restarts kept some decision levels
+  pub reused_trails: u32,
+  /// how many times did this solver switch between focused and stable search
+  pub mode_switches: u32,
   /// how many clauses did this solver learn
   pub clauses_learned: usize,
   /// how many propogations were there
   pub propogations: u32,
+  /// how many watched clauses were visited while propagating
+  pub ticks: u64,
+  /// how many decisions were picked randomly
+  pub random_decisions: u32,
+  /// how many variables were eliminated by preprocessing
+  pub eliminated_vars: u32,
+  /// how many variables were replaced by an equivalent literal
+  pub substituted_vars: u32,
+  /// how many clauses were removed because another clause subsumed them
+  pub subsumed_clauses: u32,
+  /// how many clauses were strengthened by self-subsuming resolution
+  pub strengthened_clauses: u32,
+  /// how many blocked clauses were removed
+  pub blocked_clauses: u32,
+  /// how many clauses were shortened by vivification
+  pub vivified_clauses: u32,
+  /// how many literals were removed by vivification
+  pub vivified_literals: u32,
+  /// how many literals were found to fail while probing
+  pub failed_literals: u32,
+  /// how many literals were implied by both values of a variable while probing
+  pub probed_units: u32,
+  /// how many hyper-binary resolvents were added while probing
+  pub hyper_binaries: u32,
 
   /// For all the learned clauses, how many literals were there
   pub learnt_literals: u32,
+  /// How many literals were removed from learnt clauses by recursive minimization
+  pub minimized_literals: u32,
+  /// How many literals were removed from learnt clauses by shrinking
+  pub shrunk_literals: u32,
+  /// How many literals were removed from learnt clauses using binary clauses
+  pub binary_minimized_literals: u32,
 
   /// The start time of this solver
   pub start_time: Instant,
@@ -24,22 +60,79 @@ impl Stats {
   pub fn new() -> Self {
     Self {
       restarts: 0,
+      chrono_backtracks: 0,
+      reused_trails: 0,
+      mode_switches: 0,
       clauses_learned: 0,
       propogations: 0,
+      ticks: 0,
+      random_decisions: 0,
+      eliminated_vars: 0,
+      substituted_vars: 0,
+      subsumed_clauses: 0,
+      strengthened_clauses: 0,
+      blocked_clauses: 0,
+      vivified_clauses: 0,
+      vivified_literals: 0,
+      failed_literals: 0,
+      probed_units: 0,
+      hyper_binaries: 0,
       learnt_literals: 0,
+      minimized_literals: 0,
+      shrunk_literals: 0,
+      binary_minimized_literals: 0,
       start_time: Instant::now(),
     }
   }
   pub fn record_restart(&mut self) { self.restarts += 1; }
+  pub fn record_chrono_backtrack(&mut self) { self.chrono_backtracks += 1; }
+  pub fn record_reused_trail(&mut self) { self.reused_trails += 1; }
+  pub fn record_mode_switch(&mut self) { self.mode_switches += 1; }
   pub fn record_learned_clause(&mut self) { self.clauses_learned += 1; }
   pub fn record_propogation(&mut self) { self.propogations += 1; }
+  pub fn record_ticks(&mut self, n: usize) { self.ticks += n as u64; }
+  pub fn record_random_decision(&mut self) { self.random_decisions += 1; }
+  pub fn record_eliminated_vars(&mut self, n: u32) { self.eliminated_vars += n; }
+  pub fn record_substituted_vars(&mut self, n: u32) { self.substituted_vars += n; }
+  pub fn record_subsumed_clauses(&mut self, n: u32) { self.subsumed_clauses += n; }
+  pub fn record_strengthened_clauses(&mut self, n: u32) { self.strengthened_clauses += n; }
+  pub fn record_blocked_clauses(&mut self, n: u32) { self.blocked_clauses += n; }
+  pub fn record_vivified_clause(&mut self, removed: u32) {
+    self.vivified_clauses += 1;
+    self.vivified_literals += removed;
+  }
+  pub fn record_failed_literal(&mut self) { self.failed_literals += 1; }
+  pub fn record_probed_unit(&mut self) { self.probed_units += 1; }
+  pub fn record_hyper_binary(&mut self) { self.hyper_binaries += 1; }
   pub fn record_learnt_literals(&mut self, n: u32) { self.learnt_literals += n; }
+  pub fn record_minimized_literals(&mut self, n: u32) { self.minimized_literals += n; }
+  pub fn record_shrunk_literals(&mut self, n: u32) { self.shrunk_literals += n; }
+  pub fn record_binary_minimized_literals(&mut self, n: u32) {
+    self.binary_minimized_literals += n;
+  }
   /// Prints the rate for this solver given some unit time
   pub fn rate(&self, unit_time: Duration) {
     let total_time = self.start_time.elapsed();
     let elapsed_units = total_time.div_duration_f64(unit_time);
     println!("=======================[Problem Statistics]=====================");
     println!("Restarts {}", self.restarts);
+    println!("Mode switches {}", self.mode_switches);
+    println!(
+      "Eliminated variables {}, substituted {}",
+      self.eliminated_vars, self.substituted_vars
+    );
+    println!(
+      "Subsumed clauses {}, strengthened {}, blocked {}",
+      self.subsumed_clauses, self.strengthened_clauses, self.blocked_clauses
+    );
+    println!(
+      "Failed literals {}, probed units {}, hyper-binary resolvents {}",
+      self.failed_literals, self.probed_units, self.hyper_binaries
+    );
+    println!(
+      "Vivified clauses {} ({} literals)",
+      self.vivified_clauses, self.vivified_literals
+    );
     let clause_rate = (self.clauses_learned as f64) / elapsed_units;
     println!(
       "Conflicts {} ({}/{:?})",
@@ -50,6 +143,13 @@ impl Stats {
       "Propogations: {} ({}/{:?})",
       self.propogations, prop_rate as u32, unit_time
     );
+    println!(
+      "Learnt literals: {} (minimized {}, shrunk {}, binary {})",
+      self.learnt_literals,
+      self.minimized_literals,
+      self.shrunk_literals,
+      self.binary_minimized_literals
+    );
     println!("Total time: {:?}", total_time);
   }
   pub fn csv<S: AsRef<str>>(&self, name: S, sat: bool) {
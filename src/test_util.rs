@@ -0,0 +1,120 @@
+//! Helpers for unit tests, which check results against brute force enumeration.
+use crate::{Literal, Options, Rng, Solver};
+use std::{fs, path::PathBuf};
+
+/// Returns random clauses over vars variables, each with between 1 and max_len distinct
+/// variables.
+pub fn random_clauses(rng: &mut Rng, vars: u32, clauses: usize, max_len: u32) -> Vec<Vec<Literal>> {
+  (0..clauses)
+    .map(|_| {
+      let len = 1 + rng.below(max_len.min(vars));
+      let mut clause: Vec<Literal> = vec![];
+      while clause.len() < len as usize {
+        let lit = Literal::new(rng.below(vars), rng.next_bool());
+        if clause.iter().all(|l| l.var() != lit.var()) {
+          clause.push(lit);
+        }
+      }
+      clause
+    })
+    .collect()
+}
+
+/// Returns every assignment to vars variables, in order of their bits.
+pub fn assignments(vars: u32) -> impl Iterator<Item = Vec<Option<bool>>> {
+  (0..1u32 << vars).map(move |bits| (0..vars).map(|var| Some(bits >> var & 1 == 1)).collect())
+}
+
+/// Returns whether a model satisfies every clause.
+pub fn satisfies(model: &[Option<bool>], clauses: &[Vec<Literal>]) -> bool {
+  clauses
+    .iter()
+    .all(|clause| clause.iter().any(|lit| lit.assn(model) == Some(true)))
+}
+
+/// Returns every assignment to vars variables which satisfies every clause.
+pub fn models(vars: u32, clauses: &[Vec<Literal>]) -> Vec<Vec<Option<bool>>> {
+  assignments(vars)
+    .filter(|model| satisfies(model, clauses))
+    .collect()
+}
+
+/// Returns the clauses of DIMACs output, skipping its header.
+pub fn dimacs_clauses(dimacs: &[u8]) -> Vec<Vec<Literal>> {
+  String::from_utf8_lossy(dimacs)
+    .lines()
+    .skip(1)
+    .map(|line| {
+      line
+        .split_whitespace()
+        .map(|v| v.parse::<i32>().unwrap())
+        .filter(|&v| v != 0)
+        .map(Literal::from)
+        .collect()
+    })
+    .collect()
+}
+
+/// Writes contents to a file in the temporary directory, named so that tests running at the
+/// same time do not share files.
+pub fn temp_file(name: &str, contents: &str) -> PathBuf {
+  let path = std::env::temp_dir().join(format!("satsuma-{}-{}", std::process::id(), name));
+  fs::write(&path, contents).unwrap();
+  path
+}
+
+/// Solves random formulas over up to 12 variables with these options, checking whether each is
+/// satisfiable, its model, and a few queries under assumptions against enumeration.
+/// Only even variables are assumed, and they are frozen so that preprocessing keeps them.
+pub fn check_solver(options: &Options, seed: u64) {
+  let mut rng = Rng::new(seed);
+  for i in 0..200 {
+    let vars = 1 + i % 12;
+    let num_clauses = rng.below(6 * vars) as usize;
+    let clauses = random_clauses(&mut rng, vars, num_clauses, 3);
+    let expected = models(vars, &clauses);
+    let mut solver = Solver::with_options(options.clone());
+    solver.resize(vars);
+    for var in (0..vars).step_by(2) {
+      solver.set_frozen(var, true);
+    }
+    for clause in &clauses {
+      solver.add_clause(clause);
+    }
+    assert_eq!(solver.solve(), !expected.is_empty(), "{:?}", clauses);
+    if !expected.is_empty() {
+      assert!(
+        satisfies(solver.final_assignments(), &clauses),
+        "{:?}",
+        clauses
+      );
+    }
+    for _ in 0..3 {
+      let assumptions = (0..vars)
+        .step_by(2)
+        .filter_map(|var| match rng.below(3) {
+          0 => None,
+          b => Some(Literal::new(var, b == 1)),
+        })
+        .collect::<Vec<_>>();
+      let assumed =
+        |model: &[Option<bool>]| assumptions.iter().all(|lit| lit.assn(model) == Some(true));
+      let sat = expected.iter().any(|model| assumed(model));
+      assert_eq!(
+        solver.solve_with_assumptions(&assumptions),
+        sat,
+        "{:?} {:?}",
+        clauses,
+        assumptions
+      );
+      if sat {
+        let model = solver.final_assignments();
+        assert!(
+          satisfies(model, &clauses) && assumed(model),
+          "{:?}",
+          clauses
+        );
+      }
+    }
+  }
+}
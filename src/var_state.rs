@@ -1,6 +1,6 @@
 extern crate priority_queue;
 
-use crate::{CRef, Database};
+use crate::{CRef, Database, Rng};
 use priority_queue::PriorityQueue;
 use rustc_hash::FxHasher;
 use std::hash::BuildHasherDefault;
@@ -25,10 +25,15 @@ pub struct VariableState {
 
   /// How much to increment the activity each time a variable is seen
   pub inc_amt: f32,
+
+  /// If set, new variables start with a small random activity instead of 0
+  pub random_init: Option<Rng>,
 }
 
 pub const DEFAULT_DECAY_RATE: f32 = 1.2;
 pub const DEFAULT_INC_AMT: f32 = 1.0;
+/// Upper bound on random initial activities, so they only break ties between unseen variables
+pub const RANDOM_INIT_SCALE: f32 = 1e-5;
 
 impl VariableState {
   pub fn new() -> Self {
@@ -36,6 +41,7 @@ impl VariableState {
       priorities: PriorityQueue::with_default_hasher(),
       decay_rate: DEFAULT_DECAY_RATE,
       inc_amt: DEFAULT_INC_AMT,
+      random_init: None,
     }
   }
   /// decays the current occurrence account
@@ -58,29 +64,60 @@ impl VariableState {
       self.increase_var_activity(lit.var() as u32);
     }
   }
+  /// Returns the current activity of this variable, regardless of whether it can be picked.
+  pub fn activity(&self, var: u32) -> f32 {
+    self
+      .priorities
+      .get_priority(&var)
+      .map_or(0.0, |&Priority(p)| p.abs())
+  }
+  /// Overwrites the activity of this variable, retaining whether it can be picked.
+  pub fn set_activity(&mut self, var: u32, act: f32) {
+    self.priorities.change_priority_by(&var, |Priority(curr)| {
+      if curr.is_sign_negative() {
+        Priority(-(act + f32::EPSILON))
+      } else {
+        Priority(act)
+      }
+    });
+  }
   pub fn enable(&mut self, var: u32) {
     self
       .priorities
       .change_priority_by(&var, |Priority(curr)| Priority(curr.abs()));
   }
-  /// returns the variable with highest priority
+  /// Returns the variable with highest priority without taking it, or None if every variable
+  /// has been taken.
+  pub fn peek_highest_prio(&self) -> Option<u32> {
+    match self.priorities.peek()? {
+      (_, Priority(p)) if p.is_sign_negative() => None,
+      (&var, _) => Some(var),
+    }
+  }
+  /// returns the variable with highest priority, or None if every variable has been taken.
   /// Modifies the internal state so that the variable cannot be picked again
   /// Until it is re-enabled
-  pub fn take_highest_prio(&mut self) -> u32 {
-    let (&var, &Priority(p)) = self.priorities.peek().unwrap();
-    debug_assert!(p.is_sign_positive(), "{:?}", self.priorities);
+  pub fn take_highest_prio(&mut self) -> Option<u32> {
+    let (&var, &Priority(p)) = self.priorities.peek()?;
+    if p.is_sign_negative() {
+      return None;
+    }
     self
       .priorities
       .change_priority(&var, Priority(-(p + f32::EPSILON)));
     // assert!(self.evicted[next.0 as usize].replace(next.1).is_none());
-    var
+    Some(var)
   }
 
   pub fn clear(&mut self) { self.priorities.clear(); }
+  /// Adds any variables below `vars` which are not yet tracked.
   pub fn resize(&mut self, vars: u32) {
-    assert!(self.priorities.is_empty());
-    for i in 0..vars {
-      self.priorities.push(i, Priority(0.0));
+    for i in self.priorities.len() as u32..vars {
+      let act = match self.random_init.as_mut() {
+        None => 0.0,
+        Some(rng) => rng.next_f32() * RANDOM_INIT_SCALE,
+      };
+      self.priorities.push(i, Priority(act));
     }
   }
 }
@@ -46,6 +46,36 @@ impl WatchList {
     self.add_clause_with_lits(cref, l_0, l_1);
     None
   }
+  /// Returns the clauses watched by a literal, along with the other watched literal of each.
+  pub fn watches(&self, lit: Literal) -> impl Iterator<Item = (&CRef, &Literal)> + '_ {
+    self.occs[lit.raw() as usize].iter()
+  }
+  /// Returns how many clauses are watched by a literal.
+  pub fn num_watches(&self, lit: Literal) -> usize { self.occs[lit.raw() as usize].len() }
+  /// Iterates over every watched clause once.
+  pub fn clauses(&self) -> impl Iterator<Item = CRef> + '_ {
+    self.occs.iter().enumerate().flat_map(|(l_0, watches)| {
+      watches
+        .iter()
+        .filter(move |(_, l_1)| (l_0 as u32) < l_1.raw())
+        .map(|(&cref, _)| cref)
+    })
+  }
+  /// Replaces each watched clause with the clause it was moved to.
+  pub fn relocate(&mut self, moved: impl Fn(CRef) -> CRef) {
+    for watches in self.occs.iter_mut() {
+      *watches = watches
+        .drain()
+        .map(|(cref, lit)| (moved(cref), lit))
+        .collect();
+    }
+  }
+  /// Stops watching a clause.
+  pub fn unwatch(&mut self, cref: CRef, db: &Database) {
+    for lit in cref.iter(db) {
+      self.occs[lit.raw() as usize].remove(&cref);
+    }
+  }
   pub fn set<CB>(&mut self, l_0: Literal, assns: &[Option<bool>], db: &Database, cb: CB)
   where
     CB: FnMut(CRef, Literal), {
@@ -155,7 +185,6 @@ impl WatchList {
   }
   pub fn clear(&mut self) { self.occs.clear(); }
   pub fn resize(&mut self, vars: u32) {
-    assert!(self.occs.is_empty());
     self.occs.resize_with((vars as usize) << 1, || {
       HashMap::with_hasher(Default::default())
     });